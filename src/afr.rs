@@ -0,0 +1,177 @@
+use byteorder::{BigEndian, ByteOrder};
+
+use error::{GlusterXattrError, Result};
+use key::XattrKey;
+use target::XattrTarget;
+
+/// AFR changelog(pending counters) stored in `trusted.afr.<volname>-client-<N>`
+/// and `trusted.afr.dirty`
+///
+/// Non zero counter on a brick for client N means the brick accuses
+/// the Nth brick of the replica set of missing those operations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AfrChangelog {
+    pub data: u32,
+    pub metadata: u32,
+    pub entry: u32,
+}
+
+impl AfrChangelog {
+    pub fn new(data: u32, metadata: u32, entry: u32) -> AfrChangelog {
+        AfrChangelog { data, metadata, entry }
+    }
+
+    /// `true` if no operations are pending
+    pub fn is_clean(&self) -> bool {
+        *self == AfrChangelog::default()
+    }
+
+    pub(crate) fn decode(xattr_name: &str, value: &[u8]) -> Result<AfrChangelog> {
+        if value.len() != 12 {
+            return Err(GlusterXattrError::Malformed {
+                name: xattr_name.to_string(),
+                expected: 12,
+                actual: value.len(),
+            });
+        }
+        Ok(AfrChangelog {
+            data: BigEndian::read_u32(&value[0..4]),
+            metadata: BigEndian::read_u32(&value[4..8]),
+            entry: BigEndian::read_u32(&value[8..12]),
+        })
+    }
+
+    pub(crate) fn encode(&self) -> [u8; 12] {
+        let mut buf = [0; 12];
+        BigEndian::write_u32(&mut buf[0..4], self.data);
+        BigEndian::write_u32(&mut buf[4..8], self.metadata);
+        BigEndian::write_u32(&mut buf[8..12], self.entry);
+        buf
+    }
+}
+
+/// Get AFR pending counters(`trusted.afr.<volname>-client-<N>`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_afr_pending;
+///
+/// fn main() {
+///     let res = get_afr_pending("/bricks/b1/f1", "gv0", 1);
+///     match res {
+///         Ok(v) => println!("Pending: {:?}", v),
+///         Err(e) => println!("Failed to get AFR pending: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_afr_pending<P: XattrTarget>(path: P, volume: &str, client: u32) -> Result<AfrChangelog> {
+    let key = XattrKey::AfrPending(volume.to_string(), client).to_string();
+    let v = ::get_xattr(&path, &key)?;
+    AfrChangelog::decode(&key, &v)
+}
+
+/// Set AFR pending counters(`trusted.afr.<volname>-client-<N>`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{set_afr_pending, AfrChangelog};
+///
+/// fn main() {
+///     let res = set_afr_pending("/bricks/b1/f1", "gv0", 1, &AfrChangelog::default());
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set AFR pending: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_afr_pending<P: XattrTarget>(path: P, volume: &str, client: u32, changelog: &AfrChangelog) -> Result<()> {
+    let key = XattrKey::AfrPending(volume.to_string(), client).to_string();
+    ::set_xattr(&path, &key, &changelog.encode())
+}
+
+/// Get AFR dirty counters(`trusted.afr.dirty`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_afr_dirty;
+///
+/// fn main() {
+///     let res = get_afr_dirty("/bricks/b1/f1");
+///     match res {
+///         Ok(v) => println!("Dirty: {:?}", v),
+///         Err(e) => println!("Failed to get AFR dirty: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_afr_dirty<P: XattrTarget>(path: P) -> Result<AfrChangelog> {
+    let key = XattrKey::AfrDirty.to_string();
+    let v = ::get_xattr(&path, &key)?;
+    AfrChangelog::decode(&key, &v)
+}
+
+/// List all the AFR xattrs(`trusted.afr.*`) set on the file, dirty
+/// first and then the pending xattrs by volume and client number
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::list_afr_keys;
+///
+/// fn main() {
+///     match list_afr_keys("/bricks/b1/f1") {
+///         Ok(keys) => for k in keys { println!("{}", k) },
+///         Err(e) => println!("Failed to list AFR xattrs: {}", e)
+///     }
+/// }
+/// ```
+pub fn list_afr_keys<P: XattrTarget>(path: P) -> Result<Vec<XattrKey>> {
+    let names = path.list_raw()?;
+    let mut keys: Vec<XattrKey> = names
+        .iter()
+        .filter_map(|n| n.to_str())
+        .filter_map(|n| n.parse().ok())
+        .filter(|k| matches!(*k, XattrKey::AfrPending(..) | XattrKey::AfrDirty))
+        .collect();
+    sort_afr_keys(&mut keys);
+    Ok(keys)
+}
+
+fn sort_afr_keys(keys: &mut [XattrKey]) {
+    keys.sort_by_key(|k| match *k {
+        XattrKey::AfrPending(ref vol, client) => (1, vol.clone(), client),
+        _ => (0, String::new(), 0),
+    });
+}
+
+#[test]
+fn test_afr_changelog_encode_decode() {
+    let changelog = AfrChangelog::new(1, 0, 2);
+    assert_eq!([0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2], changelog.encode());
+
+    assert_eq!((), ::set_xattr("./testfile", "user.afr.gv0-client-0", &changelog.encode()).unwrap());
+    let v = ::get_xattr("./testfile", "user.afr.gv0-client-0").unwrap();
+    assert_eq!(changelog, AfrChangelog::decode("user.afr.gv0-client-0", &v).unwrap());
+
+    assert!(AfrChangelog::default().is_clean());
+    assert!(!changelog.is_clean());
+    assert!(AfrChangelog::decode("x", &[0; 8]).is_err());
+}
+
+#[test]
+fn test_sort_afr_keys() {
+    let pending = |client| XattrKey::AfrPending("gv0".to_string(), client);
+    let mut keys = vec![pending(10), pending(2), XattrKey::AfrDirty, pending(0)];
+    sort_afr_keys(&mut keys);
+    assert_eq!(vec![XattrKey::AfrDirty, pending(0), pending(2), pending(10)], keys);
+}
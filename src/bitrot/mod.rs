@@ -0,0 +1,259 @@
+//! Bit-rot detection xattrs
+//!
+//! Unlike the other Gluster xattrs the versions are stored in the host
+//! byte order(little-endian), the timestamp is in network byte order.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use error::{GlusterXattrError, Result};
+use gfid::Gfid;
+use handle::HANDLE_DIR;
+use key::XattrKey;
+use target::XattrTarget;
+use xtime::Xtime;
+
+mod scrub;
+
+pub use self::scrub::{scrub_brick, scrub_file, ScrubOptions, ScrubReport, ScrubStatus};
+
+/// Signature type of SHA256 signatures
+pub const BITROT_HASH_SHA256: u8 = 1;
+
+/// Directory in `.glusterfs` where the bit-rot stub records the bad
+/// objects, one entry named by the GFID per object
+const QUARANTINE_DIR: &str = "quarantine";
+
+/// Signature computed by the bit-rot daemon(`trusted.bit-rot.signature`)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitrotSignature {
+    pub hash_type: u8,
+    /// Object version the signature was computed for
+    pub signed_version: u64,
+    pub hash: Vec<u8>,
+}
+
+impl BitrotSignature {
+    pub fn decode(xattr_name: &str, value: &[u8]) -> Result<BitrotSignature> {
+        if value.len() < 9 {
+            return Err(GlusterXattrError::Malformed {
+                name: xattr_name.to_string(),
+                expected: 9,
+                actual: value.len(),
+            });
+        }
+        Ok(BitrotSignature {
+            hash_type: value[0],
+            signed_version: LittleEndian::read_u64(&value[1..9]),
+            hash: value[9..].to_vec(),
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![0; 9];
+        buf[0] = self.hash_type;
+        LittleEndian::write_u64(&mut buf[1..9], self.signed_version);
+        buf.extend_from_slice(&self.hash);
+        buf
+    }
+
+    /// Hash as lowercase hex string, as shown by `gluster volume bitrot`
+    pub fn hash_hex(&self) -> String {
+        self.hash.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Object version maintained by the bit-rot stub(`trusted.bit-rot.version`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BitrotVersion {
+    /// Incremented on the first modification after every open
+    pub ongoing_version: u64,
+    /// Boot time of the brick when the version was incremented
+    pub timestamp: Xtime,
+}
+
+impl BitrotVersion {
+    pub fn decode(xattr_name: &str, value: &[u8]) -> Result<BitrotVersion> {
+        ::check_len(xattr_name, value, 16)?;
+        Ok(BitrotVersion {
+            ongoing_version: LittleEndian::read_u64(&value[0..8]),
+            timestamp: Xtime::decode(xattr_name, &value[8..16])?,
+        })
+    }
+
+    pub fn encode(&self) -> [u8; 16] {
+        let mut buf = [0; 16];
+        LittleEndian::write_u64(&mut buf[0..8], self.ongoing_version);
+        buf[8..16].copy_from_slice(&self.timestamp.encode());
+        buf
+    }
+}
+
+/// Get bit-rot signature(`trusted.bit-rot.signature`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_bitrot_signature;
+///
+/// fn main() {
+///     let res = get_bitrot_signature("/bricks/b1/f1");
+///     match res {
+///         Ok(v) => println!("Signature: {} (version {})", v.hash_hex(), v.signed_version),
+///         Err(e) => println!("Failed to get signature: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_bitrot_signature<P: XattrTarget>(path: P) -> Result<BitrotSignature> {
+    let key = XattrKey::BitrotSignature.to_string();
+    BitrotSignature::decode(&key, &::get_xattr(&path, &key)?)
+}
+
+/// Get bit-rot object version(`trusted.bit-rot.version`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_bitrot_version;
+///
+/// fn main() {
+///     let res = get_bitrot_version("/bricks/b1/f1");
+///     match res {
+///         Ok(v) => println!("Ongoing version: {}", v.ongoing_version),
+///         Err(e) => println!("Failed to get version: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_bitrot_version<P: XattrTarget>(path: P) -> Result<BitrotVersion> {
+    let key = XattrKey::BitrotVersion.to_string();
+    BitrotVersion::decode(&key, &::get_xattr(&path, &key)?)
+}
+
+/// `true` if the scrubber marked the file as corrupted(`trusted.bit-rot.bad-file`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::is_bitrot_bad_file;
+///
+/// fn main() {
+///     match is_bitrot_bad_file("/bricks/b1/f1") {
+///         Ok(v) => println!("Corrupted: {}", v),
+///         Err(e) => println!("Failed to check: {}", e)
+///     }
+/// }
+/// ```
+pub fn is_bitrot_bad_file<P: XattrTarget>(path: P) -> Result<bool> {
+    Ok(::ignore_missing(::get_xattr(&path, &XattrKey::BitrotBadFile.to_string()))?.is_some())
+}
+
+/// Remove the quarantine entry of the object, if any
+fn remove_quarantine_entry(brick_root: &Path, gfid: &Gfid) -> Result<()> {
+    let entry = brick_root.join(HANDLE_DIR).join(QUARANTINE_DIR).join(gfid.to_string());
+    match fs::remove_file(entry) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => Ok(other?),
+    }
+}
+
+/// Remove the bad-file marker(`trusted.bit-rot.bad-file`) and the entry
+/// of the file in `.glusterfs/quarantine` of the brick after the data is
+/// restored, it is not an error if neither is present
+///
+/// The brick keeps serving the file as bad until restarted since the
+/// bit-rot stub caches the marker.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::clear_bitrot_bad_file;
+///
+/// fn main() {
+///     match clear_bitrot_bad_file("/bricks/b1", "/bricks/b1/f1") {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to clear bad-file marker: {}", e)
+///     }
+/// }
+/// ```
+pub fn clear_bitrot_bad_file<B: AsRef<Path>, P: XattrTarget>(brick_root: B, path: P) -> Result<()> {
+    match ::remove_xattr(&path, &XattrKey::BitrotBadFile.to_string()) {
+        Err(GlusterXattrError::XattrMissing(_)) => {}
+        other => other?,
+    }
+    let gfid = ::get_uuid_bytes(&path, &XattrKey::Gfid.to_string()).map(Gfid::from_bytes);
+    match ::ignore_missing(gfid)? {
+        Some(gfid) => remove_quarantine_entry(brick_root.as_ref(), &gfid),
+        None => Ok(()),
+    }
+}
+
+/// List the files on the brick marked as corrupted by the scrubber
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::list_bitrot_bad_files;
+///
+/// fn main() {
+///     match list_bitrot_bad_files("/bricks/b1") {
+///         Ok(files) => for f in files { println!("{}", f.display()) },
+///         Err(e) => println!("Failed to list bad files: {}", e)
+///     }
+/// }
+/// ```
+pub fn list_bitrot_bad_files<B: AsRef<Path>>(brick_root: B) -> Result<Vec<PathBuf>> {
+    let brick_root = brick_root.as_ref();
+    let mut bad = vec![];
+    ::walk::walk(brick_root, brick_root, &mut |path, meta| {
+        if meta.is_file() && is_bitrot_bad_file(path)? {
+            bad.push(path.to_path_buf());
+        }
+        Ok(())
+    })?;
+    Ok(bad)
+}
+
+#[test]
+fn test_bitrot_encode_decode() {
+    let mut v = vec![0x01, 0x02, 0, 0, 0, 0, 0, 0, 0];
+    v.extend_from_slice(&[0x6d; 32]);
+    let sig = BitrotSignature::decode("x", &v).unwrap();
+    assert_eq!(BITROT_HASH_SHA256, sig.hash_type);
+    assert_eq!(2, sig.signed_version);
+    assert_eq!(32, sig.hash.len());
+    assert!(sig.hash_hex().starts_with("6d6d"));
+    assert_eq!(v, sig.encode());
+    assert!(BitrotSignature::decode("x", &v[..5]).is_err());
+
+    let v = [0x02, 0, 0, 0, 0, 0, 0, 0, 0x57, 0xfe, 0x4a, 0x3f, 0x00, 0x0c, 0x5b, 0x2a];
+    let ver = BitrotVersion::decode("x", &v).unwrap();
+    assert_eq!(2, ver.ongoing_version);
+    assert_eq!(Xtime(0x57fe4a3f, 0x000c5b2a), ver.timestamp);
+    assert_eq!(v, ver.encode());
+}
+
+#[test]
+fn test_remove_quarantine_entry() {
+    let brick = ::testutil::TempDir::new("quarantine");
+    let gfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+    let entry = brick.join(HANDLE_DIR).join(QUARANTINE_DIR).join(gfid.to_string());
+    fs::create_dir_all(entry.parent().unwrap()).unwrap();
+    fs::File::create(&entry).unwrap();
+
+    remove_quarantine_entry(&brick, &gfid).unwrap();
+    assert!(!entry.exists());
+    remove_quarantine_entry(&brick, &gfid).unwrap();
+}
@@ -0,0 +1,272 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+use super::{BitrotSignature, BitrotVersion, BITROT_HASH_SHA256};
+use error::{GlusterXattrError, Result};
+use fd::FileXattrExt;
+
+const READ_BUF_SIZE: usize = 128 * 1024;
+
+/// Result of scrubbing one file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScrubStatus {
+    /// Content matches the signature
+    Good,
+    /// Content does not match the signature, `computed` is the hash of
+    /// the current content
+    Corrupted {
+        signature: BitrotSignature,
+        computed: Vec<u8>,
+    },
+    /// Signature or object version is not set
+    NotSigned,
+    /// File modified after signing, the signature is for an older
+    /// object version(or the file changed while scrubbing)
+    SignatureStale,
+    /// Signature type other than SHA256
+    UnsupportedHash(u8),
+}
+
+/// Options for `scrub_brick`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScrubOptions {
+    /// Number of files scrubbed in parallel
+    pub threads: usize,
+    /// Upper limit of the total read rate, `None` for unlimited
+    pub bytes_per_sec: Option<u64>,
+}
+
+impl Default for ScrubOptions {
+    fn default() -> ScrubOptions {
+        ScrubOptions { threads: 4, bytes_per_sec: None }
+    }
+}
+
+/// Summary of a brick scrub
+#[derive(Debug, Default)]
+pub struct ScrubReport {
+    /// Files matching their signature
+    pub good: usize,
+    /// Files not signed, modified after signing or with an unsupported
+    /// signature type
+    pub skipped: usize,
+    pub corrupted: Vec<PathBuf>,
+    /// Files which could not be scrubbed
+    pub errors: Vec<(PathBuf, GlusterXattrError)>,
+}
+
+/// Read rate shared by all the scrubber threads
+struct RateLimit {
+    bytes_per_sec: u64,
+    start: Instant,
+    bytes: u64,
+}
+
+impl RateLimit {
+    fn new(bytes_per_sec: u64) -> RateLimit {
+        RateLimit { bytes_per_sec, start: Instant::now(), bytes: 0 }
+    }
+}
+
+/// Account `n` bytes read and sleep until the average rate falls within
+/// the limit
+fn throttle(limit: Option<&Mutex<RateLimit>>, n: usize) {
+    let limit = match limit {
+        Some(l) => l,
+        None => return,
+    };
+    let wait = {
+        let mut l = limit.lock().unwrap();
+        l.bytes += n as u64;
+        let due = Duration::from_secs_f64(l.bytes as f64 / l.bytes_per_sec.max(1) as f64);
+        due.checked_sub(l.start.elapsed())
+    };
+    if let Some(w) = wait {
+        thread::sleep(w);
+    }
+}
+
+fn sha256<R: Read>(mut data: R, limit: Option<&Mutex<RateLimit>>) -> io::Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; READ_BUF_SIZE];
+    loop {
+        let n = match data.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buf[..n]);
+        throttle(limit, n);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Status before reading the content, `None` if the content has to be
+/// hashed and compared
+fn precheck(signature: &Option<BitrotSignature>, version: &Option<BitrotVersion>) -> Option<ScrubStatus> {
+    match (signature, version) {
+        (Some(s), Some(v)) => {
+            if s.signed_version != v.ongoing_version {
+                Some(ScrubStatus::SignatureStale)
+            } else if s.hash_type != BITROT_HASH_SHA256 {
+                Some(ScrubStatus::UnsupportedHash(s.hash_type))
+            } else {
+                None
+            }
+        }
+        _ => Some(ScrubStatus::NotSigned),
+    }
+}
+
+fn scrub(path: &Path, limit: Option<&Mutex<RateLimit>>) -> Result<ScrubStatus> {
+    // Xattrs are read from the open file so that a file replaced by a
+    // rename is not compared against another file's signature
+    let file = File::open(path)?;
+    let version = ::ignore_missing(file.get_bitrot_version())?;
+    let signature = ::ignore_missing(file.get_bitrot_signature())?;
+    if let Some(status) = precheck(&signature, &version) {
+        return Ok(status);
+    }
+
+    let computed = sha256(&file, limit)?;
+    if ::ignore_missing(file.get_bitrot_version())? != version {
+        return Ok(ScrubStatus::SignatureStale);
+    }
+
+    let signature = signature.unwrap();
+    if computed == signature.hash {
+        Ok(ScrubStatus::Good)
+    } else {
+        Ok(ScrubStatus::Corrupted { signature, computed })
+    }
+}
+
+/// Recompute the SHA256 of the file content and compare with the
+/// signature(`trusted.bit-rot.signature`). Files modified after signing
+/// are not compared.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{scrub_file, ScrubStatus};
+///
+/// fn main() {
+///     match scrub_file("/bricks/b1/f1") {
+///         Ok(ScrubStatus::Corrupted { .. }) => println!("Corrupted"),
+///         Ok(s) => println!("Status: {:?}", s),
+///         Err(e) => println!("Failed to scrub: {}", e)
+///     }
+/// }
+/// ```
+pub fn scrub_file<P: AsRef<Path>>(path: P) -> Result<ScrubStatus> {
+    scrub(path.as_ref(), None)
+}
+
+/// Scrub all the regular files of the brick, for use while the brick
+/// process is not running. Corrupted files are only reported, not
+/// marked as bad.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{scrub_brick, ScrubOptions};
+///
+/// fn main() {
+///     let opts = ScrubOptions { threads: 2, bytes_per_sec: Some(50 * 1024 * 1024) };
+///     match scrub_brick("/bricks/b1", &opts) {
+///         Ok(r) => for f in r.corrupted { println!("Corrupted: {}", f.display()) },
+///         Err(e) => println!("Failed to scrub brick: {}", e)
+///     }
+/// }
+/// ```
+pub fn scrub_brick<B: AsRef<Path>>(brick_root: B, opts: &ScrubOptions) -> Result<ScrubReport> {
+    let brick_root = brick_root.as_ref();
+    let mut files = vec![];
+    ::walk::walk(brick_root, brick_root, &mut |path, meta| {
+        if meta.is_file() {
+            files.push(path.to_path_buf());
+        }
+        Ok(())
+    })?;
+
+    let limit = opts.bytes_per_sec.map(|r| Mutex::new(RateLimit::new(r)));
+    let next = AtomicUsize::new(0);
+    let report = Mutex::new(ScrubReport::default());
+    thread::scope(|s| {
+        for _ in 0..opts.threads.max(1) {
+            s.spawn(|| {
+                while let Some(path) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let res = scrub(path, limit.as_ref());
+                    let mut report = report.lock().unwrap();
+                    match res {
+                        Ok(ScrubStatus::Good) => report.good += 1,
+                        Ok(ScrubStatus::Corrupted { .. }) => report.corrupted.push(path.clone()),
+                        Ok(_) => report.skipped += 1,
+                        // Deleted after the walk
+                        Err(GlusterXattrError::Io(ref e)) if e.kind() == io::ErrorKind::NotFound => {}
+                        Err(e) => report.errors.push((path.clone(), e)),
+                    }
+                }
+            });
+        }
+    });
+
+    let mut report = report.into_inner().unwrap();
+    report.corrupted.sort();
+    report.errors.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(report)
+}
+
+#[test]
+fn test_scrub_compare() {
+    let hello = sha256(&b"hello"[..], None).unwrap();
+    assert_eq!(
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+        BitrotSignature { hash_type: BITROT_HASH_SHA256, signed_version: 0, hash: hello.clone() }.hash_hex()
+    );
+
+    let sig = BitrotSignature { hash_type: BITROT_HASH_SHA256, signed_version: 3, hash: hello };
+    let ver = |v| BitrotVersion { ongoing_version: v, timestamp: ::Xtime(0, 0) };
+    assert_eq!(None, precheck(&Some(sig.clone()), &Some(ver(3))));
+    assert_eq!(Some(ScrubStatus::SignatureStale), precheck(&Some(sig.clone()), &Some(ver(4))));
+    assert_eq!(Some(ScrubStatus::NotSigned), precheck(&None, &Some(ver(3))));
+    assert_eq!(Some(ScrubStatus::NotSigned), precheck(&Some(sig.clone()), &None));
+    let other = BitrotSignature { hash_type: 2, ..sig };
+    assert_eq!(Some(ScrubStatus::UnsupportedHash(2)), precheck(&Some(other), &Some(ver(3))));
+
+    let limit = Mutex::new(RateLimit::new(1024 * 1024));
+    let start = Instant::now();
+    sha256(&[0u8; 256 * 1024][..], Some(&limit)).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[test]
+fn test_scrub_brick_unsigned() {
+    use std::fs;
+    use std::io::Write;
+
+    let brick = ::testutil::TempDir::new("scrub");
+    fs::create_dir_all(brick.join("d1")).unwrap();
+    for f in &["f1", "d1/f2"] {
+        File::create(brick.join(f)).unwrap().write_all(b"data").unwrap();
+    }
+
+    assert_eq!(ScrubStatus::NotSigned, scrub_file(brick.join("f1")).unwrap());
+    let report = scrub_brick(&brick, &ScrubOptions::default()).unwrap();
+    assert_eq!(0, report.good);
+    assert_eq!(2, report.skipped);
+    assert!(report.corrupted.is_empty());
+    assert!(report.errors.is_empty());
+}
@@ -0,0 +1,176 @@
+use std::path::{Path, PathBuf};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use super::layout::get_dht_layout;
+use error::{GlusterXattrError, Result};
+use key::XattrKey;
+use target::XattrTarget;
+
+fn decode_commit_hash(xattr_name: &str, value: &[u8]) -> Result<u32> {
+    let digits = value.strip_suffix(b"\0").unwrap_or(value);
+    String::from_utf8_lossy(digits).parse().map_err(|_| GlusterXattrError::MalformedValue {
+        name: xattr_name.to_string(),
+        value: String::from_utf8_lossy(value).into_owned(),
+    })
+}
+
+/// Get DHT commit hash(`trusted.glusterfs.dht.commithash`) of the volume
+/// root directory. Gluster stores it like other dict integers, as a NUL
+/// terminated decimal string, and only on the volume root, the commit
+/// hash of the other directories is the first word of their layout.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_dht_commit_hash;
+///
+/// fn main() {
+///     let res = get_dht_commit_hash("/bricks/b1");
+///     match res {
+///         Ok(v) => println!("Commit hash: {}", v),
+///         Err(e) => println!("Failed to get commit hash: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_dht_commit_hash<P: XattrTarget>(path: P) -> Result<u32> {
+    let key = XattrKey::DhtCommitHash.to_string();
+    let v = ::get_xattr(&path, &key)?;
+    decode_commit_hash(&key, &v)
+}
+
+/// Set DHT commit hash(`trusted.glusterfs.dht.commithash`) of the volume
+/// root directory
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::set_dht_commit_hash;
+///
+/// fn main() {
+///     let res = set_dht_commit_hash("/bricks/b1", 3451293048);
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set commit hash: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_dht_commit_hash<P: XattrTarget>(path: P, commit_hash: u32) -> Result<()> {
+    let mut v = commit_hash.to_string().into_bytes();
+    v.push(0);
+    ::set_xattr(&path, &XattrKey::DhtCommitHash.to_string(), &v)
+}
+
+/// Get DHT MDS xattr(`trusted.glusterfs.dht.mds`), present only on the
+/// directory in the metadata server subvolume
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{get_dht_mds, GlusterXattrError};
+///
+/// fn main() {
+///     match get_dht_mds("/bricks/b1/dir1") {
+///         Ok(v) => println!("MDS subvolume, value: {}", v),
+///         Err(GlusterXattrError::XattrMissing(_)) => println!("Not the MDS subvolume"),
+///         Err(e) => println!("Failed to get MDS xattr: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_dht_mds<P: XattrTarget>(path: P) -> Result<i32> {
+    let key = XattrKey::DhtMds.to_string();
+    let v = ::get_xattr(&path, &key)?;
+    ::check_len(&key, &v, 4)?;
+    Ok(BigEndian::read_i32(&v))
+}
+
+/// Set DHT MDS xattr(`trusted.glusterfs.dht.mds`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::set_dht_mds;
+///
+/// fn main() {
+///     let res = set_dht_mds("/bricks/b1/dir1", 0);
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set MDS xattr: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_dht_mds<P: XattrTarget>(path: P, value: i32) -> Result<()> {
+    let mut v = [0; 4];
+    BigEndian::write_i32(&mut v, value);
+    ::set_xattr(&path, &XattrKey::DhtMds.to_string(), &v)
+}
+
+/// Directory whose commit hash does not match the volume's
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitHashMismatch {
+    pub path: PathBuf,
+    /// Commit hash xattr of the brick root, commit hash of the layout
+    /// for the other directories. `None` if not set.
+    pub found: Option<u32>,
+}
+
+/// Check the commit hash of every directory on the brick against the
+/// volume's commit hash and list the directories which missed a layout
+/// commit. The commit hash xattr is checked on the brick root, the
+/// commit hash of the layout(`trusted.glusterfs.dht`) on the other
+/// directories.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::audit_commit_hash;
+///
+/// fn main() {
+///     match audit_commit_hash("/bricks/b1", 3451293048) {
+///         Ok(dirs) => for d in dirs {
+///             println!("{}: {:?}", d.path.display(), d.found)
+///         },
+///         Err(e) => println!("Failed to audit: {}", e)
+///     }
+/// }
+/// ```
+pub fn audit_commit_hash<B: AsRef<Path>>(brick_root: B, expected: u32) -> Result<Vec<CommitHashMismatch>> {
+    let brick_root = brick_root.as_ref();
+    let mut mismatches = vec![];
+    let found = ::ignore_missing(get_dht_commit_hash(brick_root))?;
+    if found != Some(expected) {
+        mismatches.push(CommitHashMismatch { path: brick_root.to_path_buf(), found });
+    }
+
+    ::walk::walk(brick_root, brick_root, &mut |path, meta| {
+        if meta.is_dir() {
+            let found = ::ignore_missing(get_dht_layout(path))?.map(|l| l.commit_hash);
+            if found != Some(expected) {
+                mismatches.push(CommitHashMismatch { path: path.to_path_buf(), found });
+            }
+        }
+        Ok(())
+    })?;
+    Ok(mismatches)
+}
+
+#[test]
+fn test_decode_commit_hash() {
+    assert_eq!(3451293048, decode_commit_hash("x", b"3451293048\0").unwrap());
+    assert_eq!(42, decode_commit_hash("x", b"42").unwrap());
+    match decode_commit_hash("x", &[1, 2, 3, 4]) {
+        Err(GlusterXattrError::MalformedValue { .. }) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(decode_commit_hash("x", b"4294967296\0").is_err());
+}
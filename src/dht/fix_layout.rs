@@ -0,0 +1,236 @@
+use std::path::Path;
+
+use super::layout::{get_dht_layout, set_dht_layout, DhtLayout, HashRange, DHT_LAYOUT_HASH_INVALID};
+use error::{GlusterXattrError, Result};
+
+/// Layout change of one subvolume, `old` is `None` if the layout is
+/// not set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutChange {
+    pub subvol: usize,
+    pub old: Option<DhtLayout>,
+    pub new: DhtLayout,
+}
+
+fn overlap(a: Option<HashRange>, b: Option<HashRange>) -> u64 {
+    match (a, b) {
+        (Some(a), Some(b)) => HashRange::new(a.start.max(b.start), a.stop.min(b.stop)).len(),
+        _ => 0,
+    }
+}
+
+/// Split the hash space into contiguous ranges proportional to the
+/// weights, subvolumes with zero weight get no range. Every weighted
+/// subvolume gets at least one hash value, and the range starting at 0
+/// at least two since `0-0` is the zeroed layout.
+fn weighted_ranges(weights: &[u64]) -> Result<Vec<Option<HashRange>>> {
+    let total: u128 = weights.iter().map(|&w| u128::from(w)).sum();
+    let count = weights.iter().filter(|&&w| w > 0).count() as u64;
+    if total == 0 {
+        return Err(GlusterXattrError::InvalidArgument("all the weights are zero".to_string()));
+    }
+    if count > u64::from(u32::MAX) {
+        return Err(GlusterXattrError::InvalidArgument(format!(
+            "{} weighted subvolumes for {} hash ranges",
+            count,
+            u32::MAX
+        )));
+    }
+
+    let mut remaining = count;
+    let mut cumulative: u128 = 0;
+    let mut start: u64 = 0;
+    let mut ranges = Vec::with_capacity(weights.len());
+
+    for &w in weights {
+        if w == 0 {
+            ranges.push(None);
+            continue;
+        }
+        remaining -= 1;
+        cumulative += u128::from(w);
+        // Leave one hash value for each of the remaining subvolumes
+        let stop = (((1u128 << 32) * cumulative / total) as u64)
+            .saturating_sub(1)
+            .max(start.max(1))
+            .min(u64::from(u32::MAX) - remaining);
+        ranges.push(Some(HashRange::new(start as u32, stop as u32)));
+        start = stop + 1;
+    }
+    Ok(ranges)
+}
+
+/// New layout for a directory, the hash space is distributed among the
+/// subvolumes proportional to `weights`(for example brick sizes, use
+/// equal weights for an even layout)
+///
+/// Like DHT's fix-layout, ranges of subvolumes with equal weights are
+/// swapped to keep as much of the current layout as possible, which
+/// reduces the data migrated by rebalance. The commit hash of the
+/// current layout is kept, subvolumes without a layout get
+/// `DHT_LAYOUT_HASH_INVALID`.
+///
+/// Fails if `current` and `weights` are not of the same length, if all
+/// the weights are zero or if there are more subvolumes with a weight
+/// than hash ranges fit in the hash space.
+pub fn plan_layout(current: &[Option<DhtLayout>], weights: &[u64]) -> Result<Vec<DhtLayout>> {
+    if current.len() != weights.len() {
+        return Err(GlusterXattrError::InvalidArgument(format!(
+            "{} weights given for {} subvolumes",
+            weights.len(),
+            current.len()
+        )));
+    }
+
+    let old: Vec<Option<HashRange>> = current.iter().map(|l| l.and_then(|l| l.range())).collect();
+    let mut new = weighted_ranges(weights)?;
+
+    for i in 0..new.len() {
+        for j in i + 1..new.len() {
+            if weights[i] != weights[j] {
+                continue;
+            }
+            let kept = overlap(old[i], new[i]) + overlap(old[j], new[j]);
+            let swapped = overlap(old[i], new[j]) + overlap(old[j], new[i]);
+            if swapped > kept {
+                new.swap(i, j);
+            }
+        }
+    }
+
+    let hash_type = current.iter().filter_map(|l| l.map(|l| l.hash_type)).next().unwrap_or(0);
+    Ok(current
+        .iter()
+        .zip(new)
+        .map(|(cur, r)| {
+            let commit_hash = cur.map_or(DHT_LAYOUT_HASH_INVALID, |l| l.commit_hash);
+            let r = r.unwrap_or_else(|| HashRange::new(0, 0));
+            DhtLayout::new(commit_hash, hash_type, r.start, r.stop)
+        })
+        .collect())
+}
+
+/// Subvolumes whose layout(range, hash type or commit hash) changes
+/// between the current and the new layout
+pub fn diff_layouts(current: &[Option<DhtLayout>], new: &[DhtLayout]) -> Vec<LayoutChange> {
+    current
+        .iter()
+        .zip(new)
+        .enumerate()
+        .filter_map(|(subvol, (&old, &new))| {
+            if old == Some(new) {
+                None
+            } else {
+                Some(LayoutChange { subvol, old, new })
+            }
+        })
+        .collect()
+}
+
+/// Compute the new layout of a directory(path relative to the brick
+/// root) for the given bricks, one brick per distribute subvolume, and
+/// write it unless `dry_run` is set
+///
+/// `weights` defaults to an even layout and must have one weight per
+/// brick. Returns the changed layouts.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::fix_layout;
+///
+/// fn main() {
+///     let bricks = ["/bricks/b1", "/bricks/b2", "/bricks/b3"];
+///     match fix_layout(&bricks, "dir1", None, true) {
+///         Ok(changes) => for c in changes { println!("{:?}", c) },
+///         Err(e) => println!("Failed to plan layout: {}", e)
+///     }
+/// }
+/// ```
+pub fn fix_layout<B, P>(bricks: &[B], dir: P, weights: Option<&[u64]>, dry_run: bool) -> Result<Vec<LayoutChange>>
+where
+    B: AsRef<Path>,
+    P: AsRef<Path>,
+{
+    let mut current = Vec::with_capacity(bricks.len());
+    for brick in bricks {
+        match get_dht_layout(::brick_join(brick, &dir)) {
+            Ok(l) => current.push(Some(l)),
+            Err(GlusterXattrError::XattrMissing(_)) => current.push(None),
+            Err(e) => return Err(e),
+        }
+    }
+
+    let even = vec![1; bricks.len()];
+    let new = plan_layout(&current, weights.unwrap_or(&even))?;
+    let changes = diff_layouts(&current, &new);
+    if !dry_run {
+        for change in &changes {
+            set_dht_layout(::brick_join(&bricks[change.subvol], &dir), &new[change.subvol])?;
+        }
+    }
+    Ok(changes)
+}
+
+#[test]
+fn test_plan_even_and_weighted_layout() {
+    let new = plan_layout(&[None, None], &[1, 1]).unwrap();
+    assert_eq!(vec![DhtLayout::new(1, 0, 0, 0x7fffffff), DhtLayout::new(1, 0, 0x80000000, 0xffffffff)], new);
+
+    let new = plan_layout(&[None, None, None], &[1, 0, 3]).unwrap();
+    assert_eq!(
+        vec![DhtLayout::new(1, 0, 0, 0x3fffffff), DhtLayout::new(1, 0, 0, 0), DhtLayout::new(1, 0, 0x40000000, 0xffffffff)],
+        new
+    );
+    assert!(plan_layout(&[None, None], &[1]).is_err());
+}
+
+#[test]
+fn test_plan_layout_small_and_zero_weights() {
+    // Share of the small subvolume rounds down to zero hash values
+    let new = plan_layout(&[None, None], &[1, 1 << 40]).unwrap();
+    assert_eq!(vec![DhtLayout::new(1, 0, 0, 1), DhtLayout::new(1, 0, 2, 0xffffffff)], new);
+    let new = plan_layout(&[None, None], &[1 << 40, 1]).unwrap();
+    assert_eq!(Some(HashRange::new(0xffffffff, 0xffffffff)), new[1].range());
+
+    let new = plan_layout(&[None, None, None], &[1 << 40, 1, 1 << 40]).unwrap();
+    assert_eq!(Some(HashRange::new(0x7fffffff, 0x7fffffff)), new[1].range());
+    let report = super::layout::check_layout(&new.iter().map(|l| Some(*l)).collect::<Vec<_>>());
+    assert!(report.is_ok());
+
+    match plan_layout(&[None, None], &[0, 0]) {
+        Err(GlusterXattrError::InvalidArgument(_)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_plan_layout_after_add_brick() {
+    // Second brick owned the first half, new third brick has no layout
+    let current = vec![
+        Some(DhtLayout::new(0x2a, 0, 0x80000000, 0xffffffff)),
+        Some(DhtLayout::new(0x2a, 0, 0, 0x7fffffff)),
+        None,
+    ];
+    let new = plan_layout(&current, &[1, 1, 1]).unwrap();
+    let report = super::layout::check_layout(&new.iter().map(|l| Some(*l)).collect::<Vec<_>>());
+    assert!(report.is_ok());
+    assert_eq!(Some(HashRange::new(0xaaaaaaaa, 0xffffffff)), new[0].range());
+    assert_eq!(Some(HashRange::new(0, 0x55555554)), new[1].range());
+    assert_eq!(vec![0x2a, 0x2a, DHT_LAYOUT_HASH_INVALID], new.iter().map(|l| l.commit_hash).collect::<Vec<_>>());
+
+    let changes = diff_layouts(&current, &new);
+    assert_eq!(3, changes.len());
+    assert_eq!(None, changes[2].old);
+}
+
+#[test]
+fn test_diff_layouts_commit_hash() {
+    let current = vec![Some(DhtLayout::new(0x2a, 0, 0, 0xffffffff))];
+    assert!(diff_layouts(&current, &[DhtLayout::new(0x2a, 0, 0, 0xffffffff)]).is_empty());
+    let changes = diff_layouts(&current, &[DhtLayout::new(0x2b, 0, 0, 0xffffffff)]);
+    assert_eq!(1, changes.len());
+    assert_eq!(current[0], changes[0].old);
+}
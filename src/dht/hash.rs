@@ -0,0 +1,172 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use super::layout::{get_dht_layout, DhtLayout};
+use error::{GlusterXattrError, Result};
+
+const DM_DELTA: u32 = 0x9e37_79b9;
+const DM_FULLROUNDS: usize = 10;
+const DM_PARTROUNDS: usize = 6;
+
+fn dm_round(rounds: usize, array: &[u32; 4], h0: &mut u32, h1: &mut u32) {
+    let mut sum: u32 = 0;
+    let mut b0 = *h0;
+    let mut b1 = *h1;
+
+    for _ in 0..rounds {
+        sum = sum.wrapping_add(DM_DELTA);
+        b0 = b0.wrapping_add(
+            (b1 << 4).wrapping_add(array[0]) ^ b1.wrapping_add(sum) ^ (b1 >> 5).wrapping_add(array[1]),
+        );
+        b1 = b1.wrapping_add(
+            (b0 << 4).wrapping_add(array[2]) ^ b0.wrapping_add(sum) ^ (b0 >> 5).wrapping_add(array[3]),
+        );
+    }
+
+    *h0 = h0.wrapping_add(b0);
+    *h1 = h1.wrapping_add(b1);
+}
+
+/// Davies-Meyer hash over TEA(`gf_dm_hashfn`) of the raw bytes
+///
+/// Same as Gluster on little-endian hosts, including the sign extension
+/// of the trailing bytes.
+pub fn dm_hash(msg: &[u8]) -> u32 {
+    let mut h0: u32 = 0x9464_a485;
+    let mut h1: u32 = 0x542e_1a94;
+    let len = msg.len() as u32;
+    let pad = (len | (len << 8)) | ((len | (len << 8)) << 16);
+
+    let mut chunks = msg.chunks_exact(16);
+    for chunk in &mut chunks {
+        let mut array = [0; 4];
+        for (j, word) in chunk.chunks_exact(4).enumerate() {
+            array[j] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        dm_round(DM_PARTROUNDS, &array, &mut h0, &mut h1);
+    }
+
+    let rest = chunks.remainder();
+    let mut words = rest.chunks_exact(4);
+    let mut array = [pad; 4];
+    let mut j = 0;
+    for word in &mut words {
+        array[j] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        j += 1;
+    }
+    for &b in words.remainder() {
+        array[j] = (array[j] << 8) | (b as i8 as u32);
+    }
+    dm_round(DM_FULLROUNDS, &array, &mut h0, &mut h1);
+
+    h0 ^ h1
+}
+
+/// Name used for hashing, rsync temp files(`.<name>.<suffix>`) hash
+/// to the same subvolume as the final name(DHT's default
+/// `rsync-hash-regex` `^\.(.+)\.[^.]+$`)
+fn rsync_friendly_name(name: &[u8]) -> &[u8] {
+    if name.len() < 4 || name[0] != b'.' {
+        return name;
+    }
+    match name.iter().rposition(|&b| b == b'.') {
+        Some(idx) if idx > 1 && idx < name.len() - 1 => &name[1..idx],
+        _ => name,
+    }
+}
+
+/// DHT hash of a file name, used to find its hashed subvolume
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::dht_hash;
+///
+/// fn main() {
+///     assert_eq!(dht_hash("file.txt"), dht_hash(".file.txt.Gs3x9a"));
+/// }
+/// ```
+pub fn dht_hash<N: AsRef<OsStr>>(name: N) -> u32 {
+    dm_hash(rsync_friendly_name(name.as_ref().as_bytes()))
+}
+
+/// Index of the subvolume whose layout range contains the hash of the
+/// name, `layouts` are the parent directory's layouts on each subvolume
+pub fn hashed_subvol<N: AsRef<OsStr>>(layouts: &[Option<DhtLayout>], name: N) -> Option<usize> {
+    let hash = dht_hash(name);
+    layouts.iter().position(|l| match l.as_ref().and_then(|l| l.range()) {
+        Some(r) => r.contains(hash),
+        None => false,
+    })
+}
+
+/// Read the layouts of the parent directory from every brick(one brick
+/// per distribute subvolume) and find the subvolume the name hashes to
+///
+/// `parent` is the directory path relative to the brick root. Returns
+/// `None` if no layout covers the hash.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::find_hashed_subvol;
+///
+/// fn main() {
+///     let bricks = ["/bricks/b1", "/bricks/b2"];
+///     match find_hashed_subvol(&bricks, "dir1", "f1") {
+///         Ok(Some(idx)) => println!("f1 belongs to {}", bricks[idx]),
+///         Ok(None) => println!("Layout has a hole"),
+///         Err(e) => println!("Failed to read layouts: {}", e)
+///     }
+/// }
+/// ```
+pub fn find_hashed_subvol<B, P, N>(bricks: &[B], parent: P, name: N) -> Result<Option<usize>>
+where
+    B: AsRef<Path>,
+    P: AsRef<Path>,
+    N: AsRef<OsStr>,
+{
+    let mut layouts = Vec::with_capacity(bricks.len());
+    for brick in bricks {
+        match get_dht_layout(::brick_join(brick, &parent)) {
+            Ok(l) => layouts.push(Some(l)),
+            Err(GlusterXattrError::XattrMissing(_)) => layouts.push(None),
+            Err(GlusterXattrError::Io(ref e)) if e.kind() == ::std::io::ErrorKind::NotFound => layouts.push(None),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(hashed_subvol(&layouts, name))
+}
+
+#[test]
+fn test_dm_hash() {
+    // Values from Gluster's gf_dm_hashfn
+    assert_eq!(0x884774a2, dm_hash(b""));
+    assert_eq!(0x3a17e4e6, dm_hash(b"a"));
+    assert_eq!(0xd3b4775a, dm_hash(b"f1"));
+    assert_eq!(0xc7426d64, dm_hash(b"file.txt"));
+    assert_eq!(0x6ecb5ada, dm_hash(b"0123456789abcdef"));
+    assert_eq!(0x7a974153, dm_hash(b"a-much-longer-file-name.tar.gz"));
+    assert_eq!(0x9e089967, dm_hash("caf\u{e9}".as_bytes()));
+}
+
+#[test]
+fn test_dht_hash_rsync_names() {
+    assert_eq!(dm_hash(b"foo.txt"), dht_hash(".foo.txt.AbC123"));
+    assert_eq!(dm_hash(b".foo"), dht_hash(".foo"));
+    assert_eq!(dm_hash(b"..x"), dht_hash("..x"));
+    assert_eq!(dm_hash(b".foo."), dht_hash(".foo."));
+
+    let layouts = vec![
+        Some(DhtLayout::new(1, 0, 0, 0x7fffffff)),
+        None,
+        Some(DhtLayout::new(1, 0, 0x80000000, 0xffffffff)),
+    ];
+    assert_eq!(Some(2), hashed_subvol(&layouts, "file.txt"));
+    assert_eq!(Some(0), hashed_subvol(&layouts, "0123456789abcdef"));
+}
@@ -0,0 +1,271 @@
+use byteorder::{BigEndian, ByteOrder};
+
+use error::{GlusterXattrError, Result};
+use key::XattrKey;
+use target::XattrTarget;
+
+/// Inclusive range of the 32 bit DHT hash space
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HashRange {
+    pub start: u32,
+    pub stop: u32,
+}
+
+impl HashRange {
+    pub fn new(start: u32, stop: u32) -> HashRange {
+        HashRange { start, stop }
+    }
+
+    /// Number of hash values in the range
+    pub fn len(&self) -> u64 {
+        if self.stop < self.start {
+            return 0;
+        }
+        u64::from(self.stop) - u64::from(self.start) + 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, hash: u32) -> bool {
+        self.start <= hash && hash <= self.stop
+    }
+}
+
+/// Commit hash of a layout that is not committed(`DHT_LAYOUT_HASH_INVALID`)
+pub const DHT_LAYOUT_HASH_INVALID: u32 = 1;
+
+/// Directory layout stored in `trusted.glusterfs.dht` on each brick
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DhtLayout {
+    /// Volume commit hash(`trusted.glusterfs.dht.commithash` of the
+    /// brick root) the layout was written for, `DHT_LAYOUT_HASH_INVALID`
+    /// if not committed
+    pub commit_hash: u32,
+    pub hash_type: u32,
+    pub start: u32,
+    pub stop: u32,
+}
+
+impl DhtLayout {
+    pub fn new(commit_hash: u32, hash_type: u32, start: u32, stop: u32) -> DhtLayout {
+        DhtLayout { commit_hash, hash_type, start, stop }
+    }
+
+    /// Hash range owned by the brick, `None` for the zeroed layout
+    /// Gluster sets on bricks which do not own any range
+    pub fn range(&self) -> Option<HashRange> {
+        if self.start == 0 && self.stop == 0 {
+            return None;
+        }
+        Some(HashRange::new(self.start, self.stop))
+    }
+
+    pub fn decode(xattr_name: &str, value: &[u8]) -> Result<DhtLayout> {
+        if value.len() != 16 {
+            return Err(GlusterXattrError::Malformed {
+                name: xattr_name.to_string(),
+                expected: 16,
+                actual: value.len(),
+            });
+        }
+        Ok(DhtLayout {
+            commit_hash: BigEndian::read_u32(&value[0..4]),
+            hash_type: BigEndian::read_u32(&value[4..8]),
+            start: BigEndian::read_u32(&value[8..12]),
+            stop: BigEndian::read_u32(&value[12..16]),
+        })
+    }
+
+    pub fn encode(&self) -> [u8; 16] {
+        let mut buf = [0; 16];
+        BigEndian::write_u32(&mut buf[0..4], self.commit_hash);
+        BigEndian::write_u32(&mut buf[4..8], self.hash_type);
+        BigEndian::write_u32(&mut buf[8..12], self.start);
+        BigEndian::write_u32(&mut buf[12..16], self.stop);
+        buf
+    }
+}
+
+/// Get DHT layout(`trusted.glusterfs.dht`) of a directory on a brick
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_dht_layout;
+///
+/// fn main() {
+///     let res = get_dht_layout("/bricks/b1/dir1");
+///     match res {
+///         Ok(v) => println!("Layout: {:?}", v.range()),
+///         Err(e) => println!("Failed to get layout: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_dht_layout<P: XattrTarget>(path: P) -> Result<DhtLayout> {
+    let key = XattrKey::DhtLayout.to_string();
+    let v = ::get_xattr(&path, &key)?;
+    DhtLayout::decode(&key, &v)
+}
+
+/// Set DHT layout(`trusted.glusterfs.dht`) of a directory on a brick
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{set_dht_layout, DhtLayout, DHT_LAYOUT_HASH_INVALID};
+///
+/// fn main() {
+///     let res = set_dht_layout("/bricks/b1/dir1", &DhtLayout::new(DHT_LAYOUT_HASH_INVALID, 0, 0, 0x7fffffff));
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set layout: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_dht_layout<P: XattrTarget>(path: P, layout: &DhtLayout) -> Result<()> {
+    ::set_xattr(&path, &XattrKey::DhtLayout.to_string(), &layout.encode())
+}
+
+/// Hash range claimed by more than one subvolume
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutOverlap {
+    pub range: HashRange,
+    pub subvols: Vec<usize>,
+}
+
+/// Result of checking the layouts of a directory across subvolumes
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LayoutReport {
+    /// Range owned by each subvolume, `None` if it owns no range
+    pub ranges: Vec<Option<HashRange>>,
+    /// Subvolumes without the layout xattr
+    pub missing: Vec<usize>,
+    /// Subvolumes whose range starts after it stops
+    pub invalid: Vec<usize>,
+    /// Hash ranges not owned by any subvolume
+    pub holes: Vec<HashRange>,
+    pub overlaps: Vec<LayoutOverlap>,
+}
+
+impl LayoutReport {
+    /// `true` if every hash is owned by exactly one subvolume
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty() && self.holes.is_empty() && self.overlaps.is_empty()
+    }
+}
+
+/// Check the layouts of one directory read from each subvolume(`None`
+/// if the xattr is missing) for holes and overlaps
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{check_layout, get_dht_layout};
+///
+/// fn main() {
+///     let layouts: Vec<_> = ["/bricks/b1/dir1", "/bricks/b2/dir1"]
+///         .iter()
+///         .map(|p| get_dht_layout(p).ok())
+///         .collect();
+///     let report = check_layout(&layouts);
+///     println!("Holes: {:?}, Overlaps: {:?}", report.holes, report.overlaps);
+/// }
+/// ```
+pub fn check_layout(layouts: &[Option<DhtLayout>]) -> LayoutReport {
+    let mut report = LayoutReport::default();
+    let mut owned = vec![];
+
+    for (idx, layout) in layouts.iter().enumerate() {
+        let range = match *layout {
+            Some(ref l) => l.range(),
+            None => {
+                report.missing.push(idx);
+                None
+            }
+        };
+        match range {
+            Some(r) if r.start > r.stop => {
+                report.invalid.push(idx);
+                report.ranges.push(None);
+            }
+            Some(r) => {
+                owned.push((r, idx));
+                report.ranges.push(Some(r));
+            }
+            None => report.ranges.push(None),
+        }
+    }
+
+    owned.sort();
+    let mut next: u64 = 0;
+    for &(r, _) in &owned {
+        if u64::from(r.start) > next {
+            report.holes.push(HashRange::new(next as u32, r.start - 1));
+        }
+        next = next.max(u64::from(r.stop) + 1);
+    }
+    if next <= u64::from(u32::MAX) {
+        report.holes.push(HashRange::new(next as u32, u32::MAX));
+    }
+
+    for (i, &(a, a_idx)) in owned.iter().enumerate() {
+        for &(b, b_idx) in &owned[i + 1..] {
+            if b.start > a.stop {
+                break;
+            }
+            report.overlaps.push(LayoutOverlap {
+                range: HashRange::new(b.start, a.stop.min(b.stop)),
+                subvols: vec![a_idx, b_idx],
+            });
+        }
+    }
+
+    report
+}
+
+#[test]
+fn test_dht_layout_encode_decode() {
+    let layout = DhtLayout::new(0x2a, 0, 0x55555555, 0xaaaaaaa9);
+    let v = layout.encode();
+    assert_eq!([0, 0, 0, 0x2a, 0, 0, 0, 0, 0x55, 0x55, 0x55, 0x55, 0xaa, 0xaa, 0xaa, 0xa9], v);
+    assert_eq!(layout, DhtLayout::decode("x", &v).unwrap());
+    assert!(DhtLayout::decode("x", &v[..12]).is_err());
+    assert_eq!(None, DhtLayout::new(1, 0, 0, 0).range());
+}
+
+#[test]
+fn test_check_layout() {
+    let report = check_layout(&[
+        Some(DhtLayout::new(1, 0, 0, 0x7fffffff)),
+        Some(DhtLayout::new(1, 0, 0x80000000, 0xffffffff)),
+    ]);
+    assert!(report.is_ok());
+    assert_eq!(Some(HashRange::new(0x80000000, 0xffffffff)), report.ranges[1]);
+
+    // Hole after add-brick, new brick without layout
+    let report = check_layout(&[
+        Some(DhtLayout::new(1, 0, 0, 0x3fffffff)),
+        Some(DhtLayout::new(1, 0, 0x80000000, 0xffffffff)),
+        None,
+    ]);
+    assert_eq!(vec![HashRange::new(0x40000000, 0x7fffffff)], report.holes);
+    assert_eq!(vec![2], report.missing);
+
+    let report = check_layout(&[
+        Some(DhtLayout::new(1, 0, 0, 0x8fffffff)),
+        Some(DhtLayout::new(1, 0, 0x80000000, 0xfffffffe)),
+    ]);
+    assert_eq!(vec![HashRange::new(0xffffffff, 0xffffffff)], report.holes);
+    assert_eq!(
+        vec![LayoutOverlap { range: HashRange::new(0x80000000, 0x8fffffff), subvols: vec![0, 1] }],
+        report.overlaps
+    );
+}
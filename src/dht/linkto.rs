@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use error::{GlusterXattrError, Result};
+use gfid::Gfid;
+use handle::handle_path;
+use key::XattrKey;
+use target::XattrTarget;
+
+/// Only the sticky bit is set on the DHT linkto files
+const LINKTO_MODE: u32 = 0o1000;
+
+/// Get the subvolume name a DHT linkto file points to(`trusted.glusterfs.dht.linkto`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_dht_linkto;
+///
+/// fn main() {
+///     let res = get_dht_linkto("/bricks/b1/f1");
+///     match res {
+///         Ok(v) => println!("Cached subvolume: {}", v),
+///         Err(e) => println!("Failed to get linkto: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_dht_linkto<P: XattrTarget>(path: P) -> Result<String> {
+    let v = ::get_xattr(&path, &XattrKey::DhtLinkto.to_string())?;
+    let end = v.iter().position(|&b| b == 0).unwrap_or(v.len());
+    Ok(String::from_utf8_lossy(&v[..end]).into_owned())
+}
+
+/// Set the subvolume name a DHT linkto file points to(`trusted.glusterfs.dht.linkto`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::set_dht_linkto;
+///
+/// fn main() {
+///     let res = set_dht_linkto("/bricks/b1/f1", "gv0-client-1");
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set linkto: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_dht_linkto<P: XattrTarget>(path: P, subvol: &str) -> Result<()> {
+    let mut v = subvol.as_bytes().to_vec();
+    v.push(0);
+    ::set_xattr(&path, &XattrKey::DhtLinkto.to_string(), &v)
+}
+
+fn has_linkto_mode(meta: &fs::Metadata) -> bool {
+    meta.file_type().is_file() && meta.len() == 0 && meta.permissions().mode() & 0o7777 == LINKTO_MODE
+}
+
+/// `true` if the file is a DHT linkto file: empty regular file with only
+/// the sticky bit set and the linkto xattr
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::is_linkto_file;
+///
+/// fn main() {
+///     match is_linkto_file("/bricks/b1/f1") {
+///         Ok(v) => println!("Linkto: {}", v),
+///         Err(e) => println!("Failed to check: {}", e)
+///     }
+/// }
+/// ```
+pub fn is_linkto_file<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let meta = fs::symlink_metadata(&path)?;
+    if !has_linkto_mode(&meta) {
+        return Ok(false);
+    }
+    match get_dht_linkto(path.as_ref()) {
+        Ok(_) => Ok(true),
+        Err(GlusterXattrError::XattrMissing(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reason a linkto file is stale
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StaleLinktoReason {
+    /// Linkto points to a subvolume not in the given list
+    UnknownSubvol,
+    /// Cached subvolume does not have the GFID
+    MissingOnTarget,
+}
+
+/// Linkto file whose target does not hold the file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaleLinkto {
+    pub path: PathBuf,
+    pub gfid: Gfid,
+    pub target: String,
+    pub reason: StaleLinktoReason,
+}
+
+fn gfid_handle_exists(brick_root: &Path, gfid: &Gfid) -> Result<bool> {
+    match fs::symlink_metadata(handle_path(brick_root, gfid)) {
+        Ok(_) => Ok(true),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(GlusterXattrError::Io(e)),
+    }
+}
+
+/// Find the linkto files under `dir`(relative to the brick root) whose
+/// target subvolume does not have the GFID
+///
+/// `subvols` maps the subvolume names used in the linkto xattr to a brick
+/// root of that subvolume.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use std::collections::HashMap;
+/// use std::path::PathBuf;
+/// use glusterxattr::find_stale_linkto_files;
+///
+/// fn main() {
+///     let mut subvols = HashMap::new();
+///     subvols.insert("gv0-client-0".to_string(), PathBuf::from("/bricks/b1"));
+///     subvols.insert("gv0-client-1".to_string(), PathBuf::from("/bricks/b2"));
+///     match find_stale_linkto_files("/bricks/b1", "/", &subvols) {
+///         Ok(stale) => for s in stale { println!("{:?}", s) },
+///         Err(e) => println!("Failed to scan: {}", e)
+///     }
+/// }
+/// ```
+pub fn find_stale_linkto_files<B, P>(brick_root: B, dir: P, subvols: &HashMap<String, PathBuf>) -> Result<Vec<StaleLinkto>>
+where
+    B: AsRef<Path>,
+    P: AsRef<Path>,
+{
+    let brick_root = brick_root.as_ref();
+    let mut stale = vec![];
+    ::walk::walk(brick_root, &::brick_join(brick_root, dir), &mut |path, meta| {
+        if !has_linkto_mode(meta) {
+            return Ok(());
+        }
+        let target = match get_dht_linkto(path) {
+            Ok(t) => t,
+            Err(GlusterXattrError::XattrMissing(_)) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let gfid = ::get_gfid_typed(path)?;
+        let reason = match subvols.get(&target) {
+            None => StaleLinktoReason::UnknownSubvol,
+            Some(root) if !gfid_handle_exists(root, &gfid)? => StaleLinktoReason::MissingOnTarget,
+            Some(_) => return Ok(()),
+        };
+        stale.push(StaleLinkto { path: path.to_path_buf(), gfid, target, reason });
+        Ok(())
+    })?;
+    Ok(stale)
+}
+
+#[test]
+fn test_linkto_mode() {
+    let dir = ::testutil::TempDir::new("linkto");
+    let path = dir.join("testfile.linkto");
+    fs::File::create(&path).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o1000)).unwrap();
+    assert!(has_linkto_mode(&fs::symlink_metadata(&path).unwrap()));
+
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o1644)).unwrap();
+    assert!(!has_linkto_mode(&fs::symlink_metadata(&path).unwrap()));
+}
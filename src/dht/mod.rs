@@ -0,0 +1,15 @@
+//! Distribute(DHT) xattrs
+
+mod commithash;
+mod fix_layout;
+mod hash;
+mod layout;
+mod linkto;
+
+pub use self::commithash::{audit_commit_hash, get_dht_commit_hash, get_dht_mds, set_dht_commit_hash, set_dht_mds,
+                            CommitHashMismatch};
+pub use self::fix_layout::{diff_layouts, fix_layout, plan_layout, LayoutChange};
+pub use self::hash::{dht_hash, dm_hash, find_hashed_subvol, hashed_subvol};
+pub use self::layout::{check_layout, get_dht_layout, set_dht_layout, DhtLayout, HashRange, LayoutOverlap, LayoutReport,
+                       DHT_LAYOUT_HASH_INVALID};
+pub use self::linkto::{find_stale_linkto_files, get_dht_linkto, is_linkto_file, set_dht_linkto, StaleLinkto, StaleLinktoReason};
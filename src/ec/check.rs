@@ -0,0 +1,156 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{get_ec_config, get_ec_dirty, get_ec_size, get_ec_version, EcConfig, EcDirty, EcVersion};
+use error::{GlusterXattrError, Result};
+use gfid::Gfid;
+
+/// State of the fragment on one brick, `None` fields are not set
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EcFragment {
+    pub gfid: Option<Gfid>,
+    pub version: Option<EcVersion>,
+    pub size: Option<u64>,
+    pub dirty: Option<EcDirty>,
+    /// Size of the fragment file, `None` for directories
+    pub fragment_size: Option<u64>,
+}
+
+/// Consistency of a file across the bricks of a disperse set, brick
+/// indexes are in the order of the volume info
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EcReport {
+    /// Fragment on each brick, `None` if the file is missing
+    pub fragments: Vec<Option<EcFragment>>,
+    pub config: Option<EcConfig>,
+    /// Bricks agreeing on GFID, version and sizes
+    pub good: Vec<usize>,
+    /// Bricks with the file missing or differing from the good bricks
+    pub stale: Vec<usize>,
+    /// Stale bricks or dirty counters are present
+    pub needs_heal: bool,
+}
+
+impl EcReport {
+    fn new(fragments: Vec<Option<EcFragment>>, config: Option<EcConfig>) -> EcReport {
+        let key = |f: &EcFragment| (f.gfid, f.version, f.size, f.fragment_size);
+
+        // Good bricks are the biggest group agreeing with each other,
+        // higher version wins the tie
+        let mut best: Option<(usize, Option<EcVersion>, &EcFragment)> = None;
+        for f in fragments.iter().filter_map(|f| f.as_ref()) {
+            let count = fragments.iter().filter(|o| o.as_ref().map(&key) == Some(key(f))).count();
+            let better = match best {
+                None => true,
+                Some((c, v, _)) => count > c || (count == c && f.version > v),
+            };
+            if better {
+                best = Some((count, f.version, f));
+            }
+        }
+
+        let good: Vec<usize> = match best {
+            Some((_, _, b)) => (0..fragments.len())
+                .filter(|&i| fragments[i].as_ref().map(&key) == Some(key(b)))
+                .collect(),
+            None => vec![],
+        };
+        let stale: Vec<usize> = (0..fragments.len()).filter(|i| !good.contains(i)).collect();
+        let dirty = fragments
+            .iter()
+            .filter_map(|f| f.as_ref().and_then(|f| f.dirty))
+            .any(|d| !d.is_clean());
+
+        EcReport { needs_heal: !stale.is_empty() || dirty, fragments, config, good, stale }
+    }
+
+    /// Number of additional bricks that can fail before the file becomes
+    /// unreadable, `None` if fewer good bricks than the data fragments
+    /// remain or the configuration is not known
+    pub fn redundancy_left(&self) -> Option<usize> {
+        let config = self.config?;
+        self.good.len().checked_sub(config.fragments() as usize)
+    }
+}
+
+fn read_fragment(path: &Path) -> Result<Option<EcFragment>> {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(GlusterXattrError::Io(e)),
+    };
+    Ok(Some(EcFragment {
+        gfid: ::ignore_missing(::get_gfid_typed(path))?,
+        version: ::ignore_missing(get_ec_version(path))?,
+        size: ::ignore_missing(get_ec_size(path))?,
+        dirty: ::ignore_missing(get_ec_dirty(path))?,
+        fragment_size: if meta.is_file() { Some(meta.len()) } else { None },
+    }))
+}
+
+/// Compare the EC xattrs, GFID and fragment size of the file on all the
+/// bricks of a disperse set and find the stale fragments
+///
+/// `file` is the path relative to the brick root.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::check_disperse;
+///
+/// fn main() {
+///     let bricks = ["/bricks/b1", "/bricks/b2", "/bricks/b3", "/bricks/b4", "/bricks/b5", "/bricks/b6"];
+///     match check_disperse(&bricks, "dir1/f1") {
+///         Ok(report) => println!("Stale: {:?}, redundancy left: {:?}", report.stale, report.redundancy_left()),
+///         Err(e) => println!("Failed to check: {}", e)
+///     }
+/// }
+/// ```
+pub fn check_disperse<B: AsRef<Path>, P: AsRef<Path>>(bricks: &[B], file: P) -> Result<EcReport> {
+    let mut fragments = Vec::with_capacity(bricks.len());
+    let mut config = None;
+    for brick in bricks {
+        let path = ::brick_join(brick, &file);
+        let fragment = read_fragment(&path)?;
+        if config.is_none() && fragment.is_some() {
+            config = ::ignore_missing(get_ec_config(&path))?;
+        }
+        fragments.push(fragment);
+    }
+    Ok(EcReport::new(fragments, config))
+}
+
+#[test]
+fn test_ec_report() {
+    let gfid: Gfid = "bb74c663-2552-41aa-a0ae-d4d94d9dd187".parse().unwrap();
+    let good = EcFragment {
+        gfid: Some(gfid),
+        version: Some(EcVersion { data: 5, metadata: 2 }),
+        size: Some(4096),
+        dirty: Some(EcDirty::default()),
+        fragment_size: Some(1024),
+    };
+    let old = EcFragment { version: Some(EcVersion { data: 4, metadata: 2 }), ..good.clone() };
+    let config = EcConfig { version: 0, algorithm: 0, gf_word_size: 8, bricks: 6, redundancy: 2, chunk_size: 512 };
+
+    let report = EcReport::new(vec![Some(good.clone()); 6], Some(config));
+    assert!(!report.needs_heal);
+    assert_eq!(Some(2), report.redundancy_left());
+
+    let report = EcReport::new(
+        vec![Some(good.clone()), Some(old), None, Some(good.clone()), Some(good.clone()), Some(good.clone())],
+        Some(config),
+    );
+    assert_eq!(vec![0, 3, 4, 5], report.good);
+    assert_eq!(vec![1, 2], report.stale);
+    assert!(report.needs_heal);
+    assert_eq!(Some(0), report.redundancy_left());
+
+    let dirty = EcFragment { dirty: Some(EcDirty { data: 1, metadata: 0 }), ..good.clone() };
+    let report = EcReport::new(vec![Some(dirty), Some(good.clone()), None, None, None], Some(config));
+    assert!(report.needs_heal);
+    assert_eq!(None, report.redundancy_left());
+}
@@ -0,0 +1,296 @@
+//! Disperse(EC) xattrs
+
+use byteorder::{BigEndian, ByteOrder};
+
+use error::{GlusterXattrError, Result};
+use key::XattrKey;
+use target::XattrTarget;
+
+mod check;
+
+pub use self::check::{check_disperse, EcFragment, EcReport};
+
+/// Data and metadata transaction versions(`trusted.ec.version`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EcVersion {
+    pub data: u64,
+    pub metadata: u64,
+}
+
+/// Data and metadata dirty counters(`trusted.ec.dirty`), non zero while
+/// an update is in progress or was interrupted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EcDirty {
+    pub data: u64,
+    pub metadata: u64,
+}
+
+impl EcDirty {
+    pub fn is_clean(&self) -> bool {
+        *self == EcDirty::default()
+    }
+}
+
+/// Decode data and metadata pair, older versions stored a single value
+/// used for both
+fn decode_pair(xattr_name: &str, value: &[u8]) -> Result<(u64, u64)> {
+    match value.len() {
+        8 => {
+            let v = BigEndian::read_u64(value);
+            Ok((v, v))
+        }
+        16 => Ok((BigEndian::read_u64(&value[0..8]), BigEndian::read_u64(&value[8..16]))),
+        len => Err(GlusterXattrError::Malformed {
+            name: xattr_name.to_string(),
+            expected: 16,
+            actual: len,
+        }),
+    }
+}
+
+fn encode_pair(data: u64, metadata: u64) -> [u8; 16] {
+    let mut buf = [0; 16];
+    BigEndian::write_u64(&mut buf[0..8], data);
+    BigEndian::write_u64(&mut buf[8..16], metadata);
+    buf
+}
+
+/// Disperse configuration packed in `trusted.ec.config`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EcConfig {
+    pub version: u8,
+    pub algorithm: u8,
+    pub gf_word_size: u8,
+    /// Total number of bricks in the disperse set
+    pub bricks: u8,
+    pub redundancy: u8,
+    pub chunk_size: u32,
+}
+
+impl EcConfig {
+    /// Number of data fragments, minimum number of good bricks required
+    pub fn fragments(&self) -> u8 {
+        self.bricks.saturating_sub(self.redundancy)
+    }
+
+    pub fn decode(xattr_name: &str, value: &[u8]) -> Result<EcConfig> {
+        ::check_len(xattr_name, value, 8)?;
+        let v = BigEndian::read_u64(value);
+        Ok(EcConfig {
+            version: (v >> 56) as u8,
+            algorithm: (v >> 48) as u8,
+            gf_word_size: (v >> 40) as u8,
+            bricks: (v >> 32) as u8,
+            redundancy: (v >> 24) as u8,
+            chunk_size: (v & 0xff_ffff) as u32,
+        })
+    }
+
+    pub fn encode(&self) -> [u8; 8] {
+        let v = u64::from(self.version) << 56
+            | u64::from(self.algorithm) << 48
+            | u64::from(self.gf_word_size) << 40
+            | u64::from(self.bricks) << 32
+            | u64::from(self.redundancy) << 24
+            | u64::from(self.chunk_size & 0xff_ffff);
+        let mut buf = [0; 8];
+        BigEndian::write_u64(&mut buf, v);
+        buf
+    }
+}
+
+/// Get EC version(`trusted.ec.version`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_ec_version;
+///
+/// fn main() {
+///     let res = get_ec_version("/bricks/b1/f1");
+///     match res {
+///         Ok(v) => println!("Data: {}, Metadata: {}", v.data, v.metadata),
+///         Err(e) => println!("Failed to get EC version: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_ec_version<P: XattrTarget>(path: P) -> Result<EcVersion> {
+    let key = XattrKey::EcVersion.to_string();
+    let (data, metadata) = decode_pair(&key, &::get_xattr(&path, &key)?)?;
+    Ok(EcVersion { data, metadata })
+}
+
+/// Set EC version(`trusted.ec.version`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{set_ec_version, EcVersion};
+///
+/// fn main() {
+///     let res = set_ec_version("/bricks/b1/f1", &EcVersion { data: 2, metadata: 1 });
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set EC version: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_ec_version<P: XattrTarget>(path: P, version: &EcVersion) -> Result<()> {
+    ::set_xattr(&path, &XattrKey::EcVersion.to_string(), &encode_pair(version.data, version.metadata))
+}
+
+/// Get EC dirty counters(`trusted.ec.dirty`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_ec_dirty;
+///
+/// fn main() {
+///     let res = get_ec_dirty("/bricks/b1/f1");
+///     match res {
+///         Ok(v) => println!("Dirty: {:?}", v),
+///         Err(e) => println!("Failed to get EC dirty: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_ec_dirty<P: XattrTarget>(path: P) -> Result<EcDirty> {
+    let key = XattrKey::EcDirty.to_string();
+    let (data, metadata) = decode_pair(&key, &::get_xattr(&path, &key)?)?;
+    Ok(EcDirty { data, metadata })
+}
+
+/// Set EC dirty counters(`trusted.ec.dirty`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{set_ec_dirty, EcDirty};
+///
+/// fn main() {
+///     let res = set_ec_dirty("/bricks/b1/f1", &EcDirty::default());
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set EC dirty: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_ec_dirty<P: XattrTarget>(path: P, dirty: &EcDirty) -> Result<()> {
+    ::set_xattr(&path, &XattrKey::EcDirty.to_string(), &encode_pair(dirty.data, dirty.metadata))
+}
+
+/// Get real size of the file(`trusted.ec.size`), the fragment on each
+/// brick is smaller
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_ec_size;
+///
+/// fn main() {
+///     let res = get_ec_size("/bricks/b1/f1");
+///     match res {
+///         Ok(v) => println!("Size: {}", v),
+///         Err(e) => println!("Failed to get EC size: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_ec_size<P: XattrTarget>(path: P) -> Result<u64> {
+    let key = XattrKey::EcSize.to_string();
+    let v = ::get_xattr(&path, &key)?;
+    ::check_len(&key, &v, 8)?;
+    Ok(BigEndian::read_u64(&v))
+}
+
+/// Set real size of the file(`trusted.ec.size`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::set_ec_size;
+///
+/// fn main() {
+///     let res = set_ec_size("/bricks/b1/f1", 1048576);
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set EC size: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_ec_size<P: XattrTarget>(path: P, size: u64) -> Result<()> {
+    let mut v = [0; 8];
+    BigEndian::write_u64(&mut v, size);
+    ::set_xattr(&path, &XattrKey::EcSize.to_string(), &v)
+}
+
+/// Get disperse configuration(`trusted.ec.config`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_ec_config;
+///
+/// fn main() {
+///     let res = get_ec_config("/bricks/b1/f1");
+///     match res {
+///         Ok(v) => println!("{}+{}, chunk size {}", v.fragments(), v.redundancy, v.chunk_size),
+///         Err(e) => println!("Failed to get EC config: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_ec_config<P: XattrTarget>(path: P) -> Result<EcConfig> {
+    let key = XattrKey::EcConfig.to_string();
+    EcConfig::decode(&key, &::get_xattr(&path, &key)?)
+}
+
+/// Set disperse configuration(`trusted.ec.config`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{get_ec_config, set_ec_config};
+///
+/// fn main() {
+///     let res = get_ec_config("/bricks/b1/f1").and_then(|c| set_ec_config("/bricks/b1/f2", &c));
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to copy EC config: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_ec_config<P: XattrTarget>(path: P, config: &EcConfig) -> Result<()> {
+    ::set_xattr(&path, &XattrKey::EcConfig.to_string(), &config.encode())
+}
+
+#[test]
+fn test_ec_encode_decode() {
+    // 4+2 volume, 512 byte chunks
+    let v = [0, 0, 8, 6, 2, 0, 2, 0];
+    let config = EcConfig::decode("x", &v).unwrap();
+    assert_eq!(
+        EcConfig { version: 0, algorithm: 0, gf_word_size: 8, bricks: 6, redundancy: 2, chunk_size: 512 },
+        config
+    );
+    assert_eq!(4, config.fragments());
+    assert_eq!(v, config.encode());
+
+    assert_eq!((3, 2), decode_pair("x", &encode_pair(3, 2)).unwrap());
+    assert_eq!((7, 7), decode_pair("x", &[0, 0, 0, 0, 0, 0, 0, 7]).unwrap());
+    assert!(decode_pair("x", &[0; 12]).is_err());
+}
@@ -0,0 +1,118 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::result;
+
+use libc;
+
+/// Result type returned by all the public functions of this crate
+pub type Result<T> = result::Result<T, GlusterXattrError>;
+
+/// Error returned by the Gluster xattr accessors
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{get_gfid, GlusterXattrError};
+///
+/// fn main() {
+///     match get_gfid("/bricks/b1/f1") {
+///         Ok(v) => println!("GFID: {}", v),
+///         Err(GlusterXattrError::XattrMissing(name)) => println!("{} is not set", name),
+///         Err(e) => println!("Failed to get GFID: {}", e)
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub enum GlusterXattrError {
+    /// System call failed, `raw_os_error` gives the errno(`EACCES`, `ENOENT`..)
+    Io(io::Error),
+    /// Xattr is not set on the file(`ENODATA`)
+    XattrMissing(String),
+    /// Xattr value is not of the expected length
+    Malformed {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// Xattr value is not in the expected format
+    MalformedValue { name: String, value: String },
+    /// Given string is not a valid UUID
+    InvalidUuid(String),
+    /// Given string is not the name of a Gluster xattr
+    UnknownXattr(String),
+    /// Xattr namespace is not supported by the filesystem(`ENOTSUP`)
+    UnsupportedNamespace(String),
+    /// Heal source could not be chosen with the given policy
+    NoHealSource(String),
+    /// Arguments given to a function are inconsistent
+    InvalidArgument(String),
+    /// `.glusterfs` handle is not a valid directory handle or does not
+    /// lead to the root directory
+    InvalidHandle(PathBuf),
+}
+
+impl GlusterXattrError {
+    /// Convert the I/O error returned while accessing the given xattr
+    pub(crate) fn from_io(err: io::Error, name: &str) -> GlusterXattrError {
+        match err.raw_os_error() {
+            Some(libc::ENODATA) => GlusterXattrError::XattrMissing(name.to_string()),
+            Some(libc::ENOTSUP) => GlusterXattrError::UnsupportedNamespace(name.to_string()),
+            _ => GlusterXattrError::Io(err),
+        }
+    }
+
+    /// errno equivalent of the error, `None` if the error is not
+    /// caused by a system call
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match *self {
+            GlusterXattrError::Io(ref e) => e.raw_os_error(),
+            GlusterXattrError::XattrMissing(_) => Some(libc::ENODATA),
+            GlusterXattrError::UnsupportedNamespace(_) => Some(libc::ENOTSUP),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GlusterXattrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GlusterXattrError::Io(ref e) => write!(f, "{}", e),
+            GlusterXattrError::XattrMissing(ref name) => write!(f, "xattr {} is not set", name),
+            GlusterXattrError::Malformed { ref name, expected, actual } => write!(
+                f,
+                "malformed value for xattr {}: expected {} bytes, got {}",
+                name, expected, actual
+            ),
+            GlusterXattrError::MalformedValue { ref name, ref value } => {
+                write!(f, "malformed value for xattr {}: {}", name, value)
+            }
+            GlusterXattrError::InvalidUuid(ref v) => write!(f, "invalid UUID: {}", v),
+            GlusterXattrError::UnknownXattr(ref name) => write!(f, "unknown xattr name: {}", name),
+            GlusterXattrError::UnsupportedNamespace(ref name) => {
+                write!(f, "xattr namespace of {} is not supported", name)
+            }
+            GlusterXattrError::NoHealSource(ref reason) => write!(f, "no heal source: {}", reason),
+            GlusterXattrError::InvalidArgument(ref reason) => write!(f, "invalid argument: {}", reason),
+            GlusterXattrError::InvalidHandle(ref path) => write!(f, "invalid handle: {}", path.display()),
+        }
+    }
+}
+
+impl error::Error for GlusterXattrError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            GlusterXattrError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GlusterXattrError {
+    fn from(err: io::Error) -> GlusterXattrError {
+        GlusterXattrError::Io(err)
+    }
+}
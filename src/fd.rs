@@ -0,0 +1,139 @@
+use std::ffi::OsString;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use xattr::FileExt;
+
+use afr::AfrChangelog;
+use bitrot::{BitrotSignature, BitrotVersion};
+use error::Result;
+use gfid::{Gfid, VolumeId};
+use target::XattrTarget;
+use xtime::Xtime;
+
+/// Raw file descriptor as an `XattrTarget`, uses `fgetxattr`/`fsetxattr`
+pub(crate) struct Fd(pub(crate) RawFd);
+
+impl AsRawFd for Fd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl FileExt for Fd {}
+
+impl XattrTarget for Fd {
+    fn get_raw(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        self.get_xattr(name)
+    }
+
+    fn set_raw(&self, name: &str, value: &[u8]) -> io::Result<()> {
+        self.set_xattr(name, value)
+    }
+
+    fn remove_raw(&self, name: &str) -> io::Result<()> {
+        self.remove_xattr(name)
+    }
+
+    fn list_raw(&self) -> io::Result<Vec<OsString>> {
+        self.list_xattr().map(|names| names.collect())
+    }
+}
+
+/// Gluster xattr accessors for open files, avoids the path lookup on
+/// every call and races with rename
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use std::fs::File;
+/// use glusterxattr::FileXattrExt;
+///
+/// fn main() {
+///     if let Ok(f) = File::open("/bricks/b1/f1") {
+///         match f.get_gfid() {
+///             Ok(v) => println!("GFID: {}", v),
+///             Err(e) => println!("Failed to get GFID: {}", e)
+///         }
+///     }
+/// }
+/// ```
+pub trait FileXattrExt: AsRawFd {
+    /// Get GFID(`trusted.gfid`)
+    fn get_gfid(&self) -> Result<Gfid> {
+        ::get_gfid_typed(Fd(self.as_raw_fd()))
+    }
+
+    /// Set GFID(`trusted.gfid`)
+    fn set_gfid(&self, gfid: &Gfid) -> Result<()> {
+        ::set_gfid_typed(Fd(self.as_raw_fd()), gfid)
+    }
+
+    /// Get Volume ID(`trusted.glusterfs.volume-id`)
+    fn get_volume_id(&self) -> Result<VolumeId> {
+        ::get_volume_id_typed(Fd(self.as_raw_fd()))
+    }
+
+    /// Set Volume ID(`trusted.glusterfs.volume-id`)
+    fn set_volume_id(&self, volume_id: &VolumeId) -> Result<()> {
+        ::set_volume_id_typed(Fd(self.as_raw_fd()), volume_id)
+    }
+
+    /// Get Xtime(`trusted.glusterfs.<mastervol_uuid>.xtime`)
+    fn get_xtime(&self, volume_id: &str) -> Result<Xtime> {
+        ::get_xtime(Fd(self.as_raw_fd()), volume_id)
+    }
+
+    /// Set Xtime(`trusted.glusterfs.<mastervol_uuid>.xtime`)
+    fn set_xtime(&self, volume_id: &str, sec: u32, usec: u32) -> Result<()> {
+        ::set_xtime(Fd(self.as_raw_fd()), volume_id, sec, usec)
+    }
+
+    /// Get Stime(`trusted.glusterfs.<mastervol_uuid>.<slavevol_uuid>.stime`)
+    fn get_stime(&self, master_volume_id: &str, slave_volume_id: &str) -> Result<Xtime> {
+        ::get_stime(Fd(self.as_raw_fd()), master_volume_id, slave_volume_id)
+    }
+
+    /// Set Stime(`trusted.glusterfs.<mastervol_uuid>.<slavevol_uuid>.stime`)
+    fn set_stime(&self, master_volume_id: &str, slave_volume_id: &str, sec: u32, usec: u32) -> Result<()> {
+        ::set_stime(Fd(self.as_raw_fd()), master_volume_id, slave_volume_id, sec, usec)
+    }
+
+    /// Get AFR pending counters(`trusted.afr.<volname>-client-<N>`)
+    fn get_afr_pending(&self, volume: &str, client: u32) -> Result<AfrChangelog> {
+        ::get_afr_pending(Fd(self.as_raw_fd()), volume, client)
+    }
+
+    /// Set AFR pending counters(`trusted.afr.<volname>-client-<N>`)
+    fn set_afr_pending(&self, volume: &str, client: u32, changelog: &AfrChangelog) -> Result<()> {
+        ::set_afr_pending(Fd(self.as_raw_fd()), volume, client, changelog)
+    }
+
+    /// Get AFR dirty counters(`trusted.afr.dirty`)
+    fn get_afr_dirty(&self) -> Result<AfrChangelog> {
+        ::get_afr_dirty(Fd(self.as_raw_fd()))
+    }
+
+    /// Get bit-rot signature(`trusted.bit-rot.signature`)
+    fn get_bitrot_signature(&self) -> Result<BitrotSignature> {
+        ::get_bitrot_signature(Fd(self.as_raw_fd()))
+    }
+
+    /// Get bit-rot object version(`trusted.bit-rot.version`)
+    fn get_bitrot_version(&self) -> Result<BitrotVersion> {
+        ::get_bitrot_version(Fd(self.as_raw_fd()))
+    }
+}
+
+impl<T: AsRawFd> FileXattrExt for T {}
+
+#[test]
+fn test_set_and_get_xtime_by_fd() {
+    let f = ::std::fs::File::open("./testfile").unwrap();
+    let fd = Fd(f.as_raw_fd());
+    assert_eq!((), ::set_xtime_stime(&fd, "user.glusterfs.fd.xtime", 300, 4).unwrap());
+    assert_eq!(Xtime(300, 4), ::get_xtime_stime("./testfile", "user.glusterfs.fd.xtime").unwrap());
+    assert!(fd.list_raw().unwrap().iter().any(|n| n == "user.glusterfs.fd.xtime"));
+}
@@ -0,0 +1,107 @@
+use std::fmt;
+use std::str::FromStr;
+
+use uuid::Uuid;
+
+use error::GlusterXattrError;
+
+macro_rules! uuid_newtype {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name([u8; 16]);
+
+        impl $name {
+            /// All zero value, never assigned to a valid object
+            pub const NULL: $name = $name([0; 16]);
+
+            /// Create from the raw 16 bytes stored in the xattr
+            pub fn from_bytes(bytes: [u8; 16]) -> $name {
+                $name(bytes)
+            }
+
+            /// Raw 16 bytes as stored in the xattr
+            pub fn as_bytes(&self) -> &[u8; 16] {
+                &self.0
+            }
+
+            /// `true` if all the bytes are zero
+            pub fn is_null(&self) -> bool {
+                *self == $name::NULL
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", Uuid::from_bytes(self.0).hyphenated())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = GlusterXattrError;
+
+            fn from_str(s: &str) -> Result<$name, GlusterXattrError> {
+                Uuid::parse_str(s)
+                    .map(|u| $name(*u.as_bytes()))
+                    .map_err(|_| GlusterXattrError::InvalidUuid(s.to_string()))
+            }
+        }
+
+        impl From<Uuid> for $name {
+            fn from(u: Uuid) -> $name {
+                $name(*u.as_bytes())
+            }
+        }
+
+        impl From<$name> for Uuid {
+            fn from(v: $name) -> Uuid {
+                Uuid::from_bytes(v.0)
+            }
+        }
+    };
+}
+
+uuid_newtype!(
+    /// GFID of a file or directory(`trusted.gfid`)
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// extern crate glusterxattr;
+    ///
+    /// use glusterxattr::Gfid;
+    ///
+    /// fn main() {
+    ///     let gfid: Gfid = "00000000-0000-0000-0000-000000000001".parse().unwrap();
+    ///     assert!(gfid.is_root());
+    /// }
+    /// ```
+    Gfid
+);
+
+uuid_newtype!(
+    /// Volume ID of a brick root(`trusted.glusterfs.volume-id`)
+    VolumeId
+);
+
+impl Gfid {
+    /// GFID of the volume root directory(`00000000-0000-0000-0000-000000000001`)
+    pub const ROOT: Gfid = Gfid([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+    /// `true` if this is the GFID of the volume root directory
+    pub fn is_root(&self) -> bool {
+        *self == Gfid::ROOT
+    }
+}
+
+#[test]
+fn test_gfid_parse_and_display() {
+    let s = "bb74c663-2552-41aa-a0ae-d4d94d9dd187";
+    let gfid: Gfid = s.parse().unwrap();
+    assert_eq!(s, gfid.to_string());
+    assert_eq!(gfid, Gfid::from(Uuid::from(gfid)));
+    assert!(!gfid.is_null() && !gfid.is_root());
+    assert!(Gfid::NULL.is_null());
+    assert_eq!("00000000-0000-0000-0000-000000000001", Gfid::ROOT.to_string());
+    assert!("bb74c663".parse::<VolumeId>().is_err());
+}
@@ -0,0 +1,192 @@
+//! GFID to path xattrs(`storage.gfid2path`)
+//!
+//! Every link of a file has a `trusted.gfid2path.<hash>` xattr with the
+//! value `<pargfid>/<basename>`, the hash is the XXH64(seed 0) of the
+//! value in hex. Directories do not have these xattrs, their parent is
+//! known from the `.glusterfs` handle.
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+
+use xxhash_rust::xxh64::xxh64;
+
+use error::{GlusterXattrError, Result};
+use gfid::Gfid;
+use handle::{dir_rel_path, handle_path};
+use key::XattrKey;
+use target::XattrTarget;
+
+/// Parent directory and name of one link of a file
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Gfid2Path {
+    pub pargfid: Gfid,
+    pub basename: OsString,
+    /// Hash from the xattr name
+    pub hash: String,
+}
+
+impl Gfid2Path {
+    pub fn new<S: AsRef<OsStr>>(pargfid: Gfid, basename: S) -> Gfid2Path {
+        let basename = basename.as_ref().to_os_string();
+        let hash = gfid2path_hash(&pargfid, &basename);
+        Gfid2Path { pargfid, basename, hash }
+    }
+
+    /// Decode the value of `trusted.gfid2path.<hash>`, a trailing NUL is
+    /// ignored
+    pub fn decode(xattr_name: &str, value: &[u8]) -> Result<Gfid2Path> {
+        let hash = match xattr_name.parse()? {
+            XattrKey::Gfid2Path(hash) => hash,
+            _ => return Err(GlusterXattrError::UnknownXattr(xattr_name.to_string())),
+        };
+        let value = value.strip_suffix(b"\0").unwrap_or(value);
+        let sep = value.iter().position(|&b| b == b'/').ok_or_else(|| GlusterXattrError::MalformedValue {
+            name: xattr_name.to_string(),
+            value: String::from_utf8_lossy(value).into_owned(),
+        })?;
+        let pargfid = String::from_utf8_lossy(&value[..sep]);
+        let pargfid = pargfid.parse().map_err(|_| GlusterXattrError::InvalidUuid(pargfid.into_owned()))?;
+        Ok(Gfid2Path { pargfid, basename: OsString::from_vec(value[sep + 1..].to_vec()), hash })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = format!("{}/", self.pargfid).into_bytes();
+        buf.extend_from_slice(self.basename.as_bytes());
+        buf
+    }
+
+    /// `true` if the hash in the xattr name matches the value
+    pub fn is_hash_valid(&self) -> bool {
+        self.hash == gfid2path_hash(&self.pargfid, &self.basename)
+    }
+
+    /// Xattr name of this entry
+    pub fn key(&self) -> XattrKey {
+        XattrKey::Gfid2Path(self.hash.clone())
+    }
+}
+
+/// Hash used in the xattr name of a link
+pub fn gfid2path_hash(pargfid: &Gfid, basename: &OsStr) -> String {
+    let mut value = format!("{}/", pargfid).into_bytes();
+    value.extend_from_slice(basename.as_bytes());
+    format!("{:016x}", xxh64(&value, 0))
+}
+
+/// List the links of a file(`trusted.gfid2path.<hash>`), sorted by
+/// parent GFID and name. Values which can not be decoded are skipped.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::list_gfid2path;
+///
+/// fn main() {
+///     match list_gfid2path("/bricks/b1/d1/f1") {
+///         Ok(links) => for l in links {
+///             println!("{}/{} valid: {}", l.pargfid, l.basename.to_string_lossy(), l.is_hash_valid())
+///         },
+///         Err(e) => println!("Failed to list gfid2path: {}", e)
+///     }
+/// }
+/// ```
+pub fn list_gfid2path<P: XattrTarget>(path: P) -> Result<Vec<Gfid2Path>> {
+    let mut links = vec![];
+    for name in path.list_raw()? {
+        let name = match name.to_str() {
+            Some(n) => n,
+            None => continue,
+        };
+        if let Ok(XattrKey::Gfid2Path(_)) = name.parse() {
+            // Removed since listed
+            let value = match ::ignore_missing(::get_xattr(&path, name))? {
+                Some(v) => v,
+                None => continue,
+            };
+            if let Ok(link) = Gfid2Path::decode(name, &value) {
+                links.push(link);
+            }
+        }
+    }
+    links.sort();
+    Ok(links)
+}
+
+/// Volume relative paths of the object `gfid` on the brick
+///
+/// A file has one path per link from the gfid2path xattrs of its
+/// `.glusterfs` handle, links whose hash does not match are ignored. The
+/// parent directories are resolved through the directory handles up to
+/// the root directory.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{resolve_gfid_paths, Gfid};
+///
+/// fn main() {
+///     let gfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+///     match resolve_gfid_paths("/bricks/b1", &gfid) {
+///         Ok(paths) => for p in paths { println!("{}", p.display()) },
+///         Err(e) => println!("Failed to resolve: {}", e)
+///     }
+/// }
+/// ```
+pub fn resolve_gfid_paths<B: AsRef<Path>>(brick_root: B, gfid: &Gfid) -> Result<Vec<PathBuf>> {
+    let brick_root = brick_root.as_ref();
+    if gfid.is_root() {
+        return Ok(vec![PathBuf::from("/")]);
+    }
+
+    let handle = handle_path(brick_root, gfid);
+    if fs::symlink_metadata(&handle)?.file_type().is_symlink() {
+        return Ok(vec![dir_rel_path(brick_root, gfid)?]);
+    }
+
+    let mut paths = vec![];
+    for link in list_gfid2path(&handle)? {
+        if link.is_hash_valid() {
+            paths.push(dir_rel_path(brick_root, &link.pargfid)?.join(&link.basename));
+        }
+    }
+    Ok(paths)
+}
+
+#[test]
+fn test_gfid2path_encode_decode() {
+    let pargfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+    let link = Gfid2Path::new(pargfid, "f1");
+    assert_eq!(16, link.hash.len());
+    assert!(link.is_hash_valid());
+    assert_eq!(b"0a118af0-3c20-4bdd-aded-694a17af6b5a/f1".to_vec(), link.encode());
+
+    let name = link.key().to_string();
+    assert_eq!(format!("trusted.gfid2path.{}", link.hash), name);
+    assert_eq!(link, Gfid2Path::decode(&name, &link.encode()).unwrap());
+
+    let mut value = link.encode();
+    value.push(0);
+    assert_eq!(link, Gfid2Path::decode(&name, &value).unwrap());
+
+    let renamed = Gfid2Path::decode(&name, b"0a118af0-3c20-4bdd-aded-694a17af6b5a/f2").unwrap();
+    assert!(!renamed.is_hash_valid());
+    match Gfid2Path::decode(&name, b"f1") {
+        Err(GlusterXattrError::MalformedValue { value, .. }) => assert_eq!("f1", value),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match Gfid2Path::decode(&name, b"0a118af0/f1") {
+        Err(GlusterXattrError::InvalidUuid(v)) => assert_eq!("0a118af0", v),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match Gfid2Path::decode("trusted.gfid", &link.encode()) {
+        Err(GlusterXattrError::UnknownXattr(n)) => assert_eq!("trusted.gfid", n),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(Gfid2Path::decode("trusted.unknown", &link.encode()).is_err());
+}
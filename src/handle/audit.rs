@@ -0,0 +1,365 @@
+use std::fs;
+use std::io;
+use std::os::unix::fs::{symlink, MetadataExt};
+use std::path::{Path, PathBuf};
+
+use super::{dir_handle_target, handle_path, HANDLE_DIR};
+use error::Result;
+use gfid::Gfid;
+use target::FollowSymlinks;
+
+/// Problem found by `audit_handles`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandleIssueKind {
+    /// Object without GFID(`trusted.gfid`), its handle can not be checked
+    NoGfid,
+    /// Handle of the object does not exist
+    MissingHandle,
+    /// Handle exists but is not the object(a different inode, or a
+    /// directory handle not leading to the directory)
+    WrongHandle,
+    /// Handle is another object with the same GFID(a file handle with
+    /// other links, or carrying the GFID itself), not repaired since
+    /// either object may be the right one
+    DuplicateGfid,
+    /// File handle with link count 1, the object was deleted without
+    /// removing its handle
+    OrphanHandle,
+}
+
+/// Change made(or planned with `dry_run`) to fix an issue
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HandleFix {
+    /// Replace the handle with a hardlink to the file
+    Link { handle: PathBuf, target: PathBuf },
+    /// Replace the handle with a symlink(directories)
+    Symlink { handle: PathBuf, target: PathBuf },
+    /// Remove the orphan handle
+    Remove(PathBuf),
+}
+
+impl HandleFix {
+    fn apply(&self) -> Result<()> {
+        let (handle, target) = match *self {
+            HandleFix::Remove(ref handle) => return Ok(fs::remove_file(handle)?),
+            HandleFix::Link { ref handle, ref target } | HandleFix::Symlink { ref handle, ref target } => {
+                (handle, target)
+            }
+        };
+        if let Some(dir) = handle.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        match fs::remove_file(handle) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            other => other?,
+        }
+        match *self {
+            HandleFix::Symlink { .. } => symlink(target, handle)?,
+            _ => fs::hard_link(target, handle)?,
+        }
+        Ok(())
+    }
+}
+
+/// Object or handle with a problem, `path` is the handle for orphans
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandleIssue {
+    pub path: PathBuf,
+    pub gfid: Option<Gfid>,
+    pub kind: HandleIssueKind,
+    /// `None` if not repairing or the issue can not be repaired
+    pub fix: Option<HandleFix>,
+}
+
+/// Whether the existing handle of `gfid` is another object with the same
+/// GFID, a file linked elsewhere or anything carrying the GFID
+fn is_duplicate(handle: &Path, existing: &fs::Metadata, gfid: Gfid) -> Result<bool> {
+    if existing.file_type().is_symlink() {
+        // Dangling symlinks carry no GFID
+        if fs::metadata(handle).is_err() {
+            return Ok(false);
+        }
+        return Ok(::ignore_missing(::get_gfid_typed(FollowSymlinks(handle)))? == Some(gfid));
+    }
+    Ok((existing.is_file() && existing.nlink() > 1) || ::ignore_missing(::get_gfid_typed(handle))? == Some(gfid))
+}
+
+struct HandleAudit<'a> {
+    brick_root: &'a Path,
+    repair: bool,
+    dry_run: bool,
+    issues: Vec<HandleIssue>,
+}
+
+impl<'a> HandleAudit<'a> {
+    fn report(&mut self, path: &Path, gfid: Option<Gfid>, kind: HandleIssueKind, fix: Option<HandleFix>) -> Result<()> {
+        let fix = if self.repair { fix } else { None };
+        if let Some(ref f) = fix {
+            if !self.dry_run {
+                f.apply()?;
+            }
+        }
+        self.issues.push(HandleIssue { path: path.to_path_buf(), gfid, kind, fix });
+        Ok(())
+    }
+
+    fn check_object(&mut self, path: &Path, meta: &fs::Metadata) -> Result<()> {
+        match ::ignore_missing(::get_gfid_typed(path))? {
+            Some(gfid) => self.check_handle(path, meta, gfid),
+            None => self.report(path, None, HandleIssueKind::NoGfid, None),
+        }
+    }
+
+    fn check_handle(&mut self, path: &Path, meta: &fs::Metadata, gfid: Gfid) -> Result<()> {
+        let handle = handle_path(self.brick_root, &gfid);
+
+        // Directory handles are symlinks, followed to compare the inode
+        let handle_meta = if meta.is_dir() {
+            fs::metadata(&handle)
+        } else {
+            fs::symlink_metadata(&handle)
+        };
+        let kind = match handle_meta {
+            Ok(ref m) if m.dev() == meta.dev() && m.ino() == meta.ino() => return Ok(()),
+            Ok(_) => HandleIssueKind::WrongHandle,
+            // Dangling directory handle
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound && fs::symlink_metadata(&handle).is_ok() => {
+                HandleIssueKind::WrongHandle
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => HandleIssueKind::MissingHandle,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut replace = true;
+        if kind == HandleIssueKind::WrongHandle {
+            let existing = fs::symlink_metadata(&handle)?;
+            if is_duplicate(&handle, &existing, gfid)? {
+                return self.report(path, Some(gfid), HandleIssueKind::DuplicateGfid, None);
+            }
+            // Only symlinks are replaced, removing any other handle
+            // drops the last link of its inode
+            replace = existing.file_type().is_symlink();
+        }
+
+        let fix = if !replace {
+            None
+        } else if meta.is_dir() {
+            // Not repairable if the parent has no GFID, reported when
+            // the parent is checked
+            let parent = path.parent().unwrap_or(self.brick_root);
+            let pgfid = if parent == self.brick_root {
+                Some(Gfid::ROOT)
+            } else {
+                ::ignore_missing(::get_gfid_typed(parent))?
+            };
+            let name = path.file_name().unwrap_or_default();
+            pgfid.map(|p| HandleFix::Symlink { handle, target: dir_handle_target(&p, name) })
+        } else {
+            Some(HandleFix::Link { handle, target: path.to_path_buf() })
+        };
+        self.report(path, Some(gfid), kind, fix)
+    }
+
+    /// File handles in `.glusterfs/<aa>/<bb>` not linked to any object.
+    /// Other directories of `.glusterfs`(indices, changelogs..) are not
+    /// handles and skipped.
+    fn check_orphans(&mut self) -> Result<()> {
+        let is_hex_dir = |e: &fs::DirEntry| {
+            let name = e.file_name();
+            let name = name.to_string_lossy();
+            name.len() == 2 && name.chars().all(|c| c.is_ascii_hexdigit()) && e.path().is_dir()
+        };
+        let handle_dir = self.brick_root.join(HANDLE_DIR);
+        let level1 = match fs::read_dir(&handle_dir) {
+            Ok(d) => d,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut handles = vec![];
+        for d1 in level1 {
+            let d1 = d1?;
+            if !is_hex_dir(&d1) {
+                continue;
+            }
+            for d2 in fs::read_dir(d1.path())? {
+                let d2 = d2?;
+                if !is_hex_dir(&d2) {
+                    continue;
+                }
+                for h in fs::read_dir(d2.path())? {
+                    let h = h?;
+                    if let Some(gfid) = h.file_name().to_str().and_then(|n| n.parse::<Gfid>().ok()) {
+                        handles.push((h.path(), gfid));
+                    }
+                }
+            }
+        }
+        handles.sort();
+
+        for (handle, gfid) in handles {
+            let meta = fs::symlink_metadata(&handle)?;
+            if meta.is_file() && meta.nlink() == 1 {
+                let fix = HandleFix::Remove(handle.clone());
+                self.report(&handle, Some(gfid), HandleIssueKind::OrphanHandle, Some(fix))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare the GFID(`trusted.gfid`) of every file and directory of the
+/// brick with its `.glusterfs` handle, and find the orphan handles
+///
+/// With `repair` missing and wrong handles are recreated and orphan
+/// handles removed, with `dry_run` as well the fixes are only reported.
+/// Wrong handles are replaced only if they are symlinks, handles which
+/// are the last link of another inode are never removed.
+/// Run while the brick process is not running.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::audit_handles;
+///
+/// fn main() {
+///     match audit_handles("/bricks/b1", true, true) {
+///         Ok(issues) => for i in issues {
+///             println!("{}: {:?}, fix: {:?}", i.path.display(), i.kind, i.fix)
+///         },
+///         Err(e) => println!("Failed to audit handles: {}", e)
+///     }
+/// }
+/// ```
+pub fn audit_handles<B: AsRef<Path>>(brick_root: B, repair: bool, dry_run: bool) -> Result<Vec<HandleIssue>> {
+    let brick_root = brick_root.as_ref();
+    let mut audit = HandleAudit { brick_root, repair, dry_run, issues: vec![] };
+    ::walk::walk(brick_root, brick_root, &mut |path, meta| audit.check_object(path, meta))?;
+    audit.check_orphans()?;
+    Ok(audit.issues)
+}
+
+#[test]
+fn test_audit_orphan_handles() {
+    let brick = ::testutil::TempDir::new("audit");
+    let linked: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+    let orphan: Gfid = "0a11963b-bbe6-49cb-bf6d-db7260ea6f72".parse().unwrap();
+    fs::create_dir_all(handle_path(&brick, &linked).parent().unwrap()).unwrap();
+    fs::create_dir_all(brick.join(HANDLE_DIR).join("indices")).unwrap();
+    fs::File::create(brick.join("f1")).unwrap();
+    fs::hard_link(brick.join("f1"), handle_path(&brick, &linked)).unwrap();
+    fs::File::create(handle_path(&brick, &orphan)).unwrap();
+
+    let check = |repair, dry_run| {
+        let issues = audit_handles(&brick, repair, dry_run).unwrap();
+        issues.into_iter().map(|i| (i.kind, i.fix)).collect::<Vec<_>>()
+    };
+    let remove = HandleFix::Remove(handle_path(&brick, &orphan));
+
+    // Objects in the temporary directory have no GFID
+    let expected = vec![(HandleIssueKind::NoGfid, None), (HandleIssueKind::OrphanHandle, None)];
+    assert_eq!(expected, check(false, false));
+    let expected = vec![(HandleIssueKind::NoGfid, None), (HandleIssueKind::OrphanHandle, Some(remove))];
+    assert_eq!(expected, check(true, true));
+    assert!(handle_path(&brick, &orphan).exists());
+    assert_eq!(expected, check(true, false));
+    assert!(!handle_path(&brick, &orphan).exists());
+    assert!(handle_path(&brick, &linked).exists());
+}
+
+#[cfg(test)]
+fn check_with_gfid(brick: &Path, path: &Path, gfid: Gfid, dry_run: bool) -> Vec<(HandleIssueKind, Option<HandleFix>)> {
+    let mut audit = HandleAudit { brick_root: brick, repair: true, dry_run, issues: vec![] };
+    audit.check_handle(path, &fs::symlink_metadata(path).unwrap(), gfid).unwrap();
+    audit.issues.into_iter().map(|i| (i.kind, i.fix)).collect()
+}
+
+#[test]
+fn test_audit_missing_file_handle() {
+    let brick = ::testutil::TempDir::new("audit-missing");
+    let gfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+    let f1 = brick.join("f1");
+    fs::File::create(&f1).unwrap();
+    let handle = handle_path(&brick, &gfid);
+
+    let expected = vec![(
+        HandleIssueKind::MissingHandle,
+        Some(HandleFix::Link { handle: handle.clone(), target: f1.clone() }),
+    )];
+    assert_eq!(expected, check_with_gfid(&brick, &f1, gfid, true));
+    assert!(!handle.exists());
+    assert_eq!(expected, check_with_gfid(&brick, &f1, gfid, false));
+    assert_eq!(fs::metadata(&f1).unwrap().ino(), fs::metadata(&handle).unwrap().ino());
+    assert!(check_with_gfid(&brick, &f1, gfid, false).is_empty());
+}
+
+#[test]
+fn test_audit_wrong_file_handle() {
+    let brick = ::testutil::TempDir::new("audit-wrong");
+    let gfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+    let handle = handle_path(&brick, &gfid);
+    fs::create_dir_all(handle.parent().unwrap()).unwrap();
+    for f in &["f1", "f2"] {
+        fs::File::create(brick.join(f)).unwrap();
+    }
+    let f1 = brick.join("f1");
+    let ino = |p: &Path| fs::symlink_metadata(p).unwrap().ino();
+
+    for &dry_run in &[true, false] {
+        // Handle linked to another file, both use the GFID
+        fs::hard_link(brick.join("f2"), &handle).unwrap();
+        let expected = vec![(HandleIssueKind::DuplicateGfid, None)];
+        assert_eq!(expected, check_with_gfid(&brick, &f1, gfid, dry_run));
+        assert_eq!(ino(&brick.join("f2")), ino(&handle));
+
+        // Handle is the last link of another inode
+        fs::remove_file(brick.join("f2")).unwrap();
+        let expected = vec![(HandleIssueKind::WrongHandle, None)];
+        assert_eq!(expected, check_with_gfid(&brick, &f1, gfid, dry_run));
+        assert!(handle.is_file());
+        fs::rename(&handle, brick.join("f2")).unwrap();
+
+        // Symlink in place of the file handle
+        symlink("f3", &handle).unwrap();
+        let expected = vec![(
+            HandleIssueKind::WrongHandle,
+            Some(HandleFix::Link { handle: handle.clone(), target: f1.clone() }),
+        )];
+        assert_eq!(expected, check_with_gfid(&brick, &f1, gfid, dry_run));
+        assert_eq!(dry_run, fs::symlink_metadata(&handle).unwrap().file_type().is_symlink());
+        fs::remove_file(&handle).unwrap();
+    }
+}
+
+#[test]
+fn test_audit_dir_handle_recreate() {
+    let brick = ::testutil::TempDir::new("audit-dir");
+    let gfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+    let root_handle = handle_path(&brick, &Gfid::ROOT);
+    let handle = handle_path(&brick, &gfid);
+    fs::create_dir_all(root_handle.parent().unwrap()).unwrap();
+    fs::create_dir_all(handle.parent().unwrap()).unwrap();
+    symlink("../../..", &root_handle).unwrap();
+    let d1 = brick.join("d1");
+    fs::create_dir_all(&d1).unwrap();
+    fs::create_dir_all(brick.join("d2")).unwrap();
+
+    let target = dir_handle_target(&Gfid::ROOT, "d1".as_ref());
+    let expected = |kind| vec![(kind, Some(HandleFix::Symlink { handle: handle.clone(), target: target.clone() }))];
+    for &dry_run in &[true, false] {
+        let _ = fs::remove_file(&handle);
+        assert_eq!(expected(HandleIssueKind::MissingHandle), check_with_gfid(&brick, &d1, gfid, dry_run));
+        assert_eq!(!dry_run, fs::read_link(&handle).ok() == Some(target.clone()));
+
+        // Handle leading to another directory
+        let _ = fs::remove_file(&handle);
+        let other = dir_handle_target(&Gfid::ROOT, "d2".as_ref());
+        symlink(&other, &handle).unwrap();
+        assert_eq!(expected(HandleIssueKind::WrongHandle), check_with_gfid(&brick, &d1, gfid, dry_run));
+        let link = fs::read_link(&handle).unwrap();
+        assert_eq!(if dry_run { other } else { target.clone() }, link);
+    }
+    assert!(check_with_gfid(&brick, &d1, gfid, false).is_empty());
+}
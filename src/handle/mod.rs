@@ -0,0 +1,182 @@
+//! `.glusterfs` handles
+//!
+//! Every object on a brick has a handle at `.glusterfs/<aa>/<bb>/<gfid>`,
+//! where `<aa>` and `<bb>` are the first two bytes of the GFID. The handle
+//! of a file is a hardlink, the handle of a directory is a symlink
+//! `../../<pa>/<pb>/<pgfid>/<name>` to the handle of its parent.
+
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+
+use error::{GlusterXattrError, Result};
+use fd::FileXattrExt;
+use gfid::Gfid;
+
+mod audit;
+
+pub use self::audit::{audit_handles, HandleFix, HandleIssue, HandleIssueKind};
+
+/// Directory of the handles at the brick root
+pub const HANDLE_DIR: &str = ".glusterfs";
+
+/// Directory depth after which a chain of directory handles is taken
+/// as a loop
+const MAX_DEPTH: usize = 4096;
+
+/// Path of the handle(`.glusterfs/<aa>/<bb>/<gfid>`) of the object
+/// `gfid` on the brick
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{handle_path, Gfid};
+///
+/// fn main() {
+///     let gfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+///     assert_eq!(
+///         "/bricks/b1/.glusterfs/0a/11/0a118af0-3c20-4bdd-aded-694a17af6b5a",
+///         handle_path("/bricks/b1", &gfid).to_str().unwrap()
+///     );
+/// }
+/// ```
+pub fn handle_path<B: AsRef<Path>>(brick_root: B, gfid: &Gfid) -> PathBuf {
+    let s = gfid.to_string();
+    brick_root.as_ref().join(HANDLE_DIR).join(&s[0..2]).join(&s[2..4]).join(&s)
+}
+
+/// Fail if the object does not carry the GFID its handle is named after
+fn verify_gfid<T: FileXattrExt>(brick_root: &Path, gfid: &Gfid, obj: &T) -> Result<()> {
+    if obj.get_gfid()? != *gfid {
+        return Err(GlusterXattrError::InvalidHandle(handle_path(brick_root, gfid)));
+    }
+    Ok(())
+}
+
+/// Open the file or directory `gfid` on the brick for reading through
+/// its handle, the GFID(`trusted.gfid`) of the opened object is verified
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use std::io::Read;
+/// use glusterxattr::{open_by_gfid, Gfid};
+///
+/// fn main() {
+///     let gfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+///     let mut data = vec![];
+///     match open_by_gfid("/bricks/b1", &gfid).and_then(|mut f| Ok(f.read_to_end(&mut data)?)) {
+///         Ok(n) => println!("Read {} bytes", n),
+///         Err(e) => println!("Failed to read: {}", e)
+///     }
+/// }
+/// ```
+pub fn open_by_gfid<B: AsRef<Path>>(brick_root: B, gfid: &Gfid) -> Result<File> {
+    let brick_root = brick_root.as_ref();
+    let file = File::open(handle_path(brick_root, gfid))?;
+    verify_gfid(brick_root, gfid, &file)?;
+    Ok(file)
+}
+
+/// Target of the handle of the directory `name` in the directory `pgfid`
+pub(crate) fn dir_handle_target(pgfid: &Gfid, name: &OsStr) -> PathBuf {
+    let s = pgfid.to_string();
+    Path::new("../..").join(&s[0..2]).join(&s[2..4]).join(&s).join(name)
+}
+
+/// Parent GFID and name from the target of a directory handle
+fn parse_dir_handle(target: &Path) -> Option<(Gfid, OsString)> {
+    let parts: Vec<Component> = target.components().collect();
+    match parts.as_slice() {
+        [Component::ParentDir, Component::ParentDir, Component::Normal(pa), Component::Normal(pb), Component::Normal(pgfid), Component::Normal(name)] =>
+        {
+            let pgfid: Gfid = pgfid.to_str()?.parse().ok()?;
+            let s = pgfid.to_string();
+            if pa.to_str()? != &s[0..2] || pb.to_str()? != &s[2..4] {
+                return None;
+            }
+            Some((pgfid, name.to_os_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Path of the directory `gfid` relative to the brick root(starting
+/// with `/`), built from the directory handles
+pub(crate) fn dir_rel_path(brick_root: &Path, gfid: &Gfid) -> Result<PathBuf> {
+    let mut names = vec![];
+    let mut gfid = *gfid;
+    while !gfid.is_root() {
+        let handle = handle_path(brick_root, &gfid);
+        if names.len() >= MAX_DEPTH {
+            return Err(GlusterXattrError::InvalidHandle(handle));
+        }
+        let target = fs::read_link(&handle)?;
+        let (pgfid, name) = match parse_dir_handle(&target) {
+            Some(v) => v,
+            None => return Err(GlusterXattrError::InvalidHandle(handle)),
+        };
+        names.push(name);
+        gfid = pgfid;
+    }
+
+    let mut path = PathBuf::from("/");
+    for name in names.iter().rev() {
+        path.push(name);
+    }
+    Ok(path)
+}
+
+/// Path of the directory `gfid` on the brick, rebuilt by following the
+/// directory handles up to the root directory. The GFID
+/// (`trusted.gfid`) of the directory at the resulting path is verified.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{resolve_dir_path, Gfid};
+///
+/// fn main() {
+///     let gfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+///     match resolve_dir_path("/bricks/b1", &gfid) {
+///         Ok(p) => println!("Directory: {}", p.display()),
+///         Err(e) => println!("Failed to resolve: {}", e)
+///     }
+/// }
+/// ```
+pub fn resolve_dir_path<B: AsRef<Path>>(brick_root: B, gfid: &Gfid) -> Result<PathBuf> {
+    let brick_root = brick_root.as_ref();
+    let path = ::brick_join(brick_root, dir_rel_path(brick_root, gfid)?);
+    verify_gfid(brick_root, gfid, &File::open(&path)?)?;
+    Ok(path)
+}
+
+#[test]
+fn test_dir_handles() {
+    use std::os::unix::fs::symlink;
+
+    let brick = ::testutil::TempDir::new("handle");
+    let d1: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+    let d2: Gfid = "af95963b-bbe6-49cb-bf6d-db7260ea6f72".parse().unwrap();
+    let bad: Gfid = "bb74c663-2552-41aa-a0ae-d4d94d9dd187".parse().unwrap();
+    for g in &[d1, d2, bad] {
+        fs::create_dir_all(handle_path(&brick, g).parent().unwrap()).unwrap();
+    }
+    symlink("../../00/00/00000000-0000-0000-0000-000000000001/d1", handle_path(&brick, &d1)).unwrap();
+    symlink(format!("../../0a/11/{}/d2", d1), handle_path(&brick, &d2)).unwrap();
+    symlink(format!("../../ff/11/{}/d3", d1), handle_path(&brick, &bad)).unwrap();
+
+    assert_eq!(
+        Some((d1, OsString::from("d2"))),
+        parse_dir_handle(&dir_handle_target(&d1, OsStr::new("d2")))
+    );
+    assert_eq!(PathBuf::from("/"), dir_rel_path(&brick, &Gfid::ROOT).unwrap());
+    assert_eq!(PathBuf::from("/d1/d2"), dir_rel_path(&brick, &d2).unwrap());
+    assert!(matches!(dir_rel_path(&brick, &bad), Err(GlusterXattrError::InvalidHandle(_))));
+}
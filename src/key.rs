@@ -0,0 +1,303 @@
+use std::fmt;
+use std::str::FromStr;
+
+use error::GlusterXattrError;
+use gfid::{Gfid, VolumeId};
+
+const GLUSTERFS_PREFIX: &str = "trusted.glusterfs";
+const AFR_PREFIX: &str = "trusted.afr";
+const EC_PREFIX: &str = "trusted.ec";
+const BITROT_PREFIX: &str = "trusted.bit-rot";
+const GFID2PATH_PREFIX: &str = "trusted.gfid2path";
+
+/// Name of a Gluster xattr
+///
+/// All the accessors in this crate build the xattr names using this type,
+/// `to_string` renders the full name and `parse` does the reverse.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::XattrKey;
+///
+/// fn main() {
+///     let key = XattrKey::Stime(
+///         "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap(),
+///         "af95963b-bbe6-49cb-bf6d-db7260ea6f72".parse().unwrap(),
+///     );
+///     assert_eq!(
+///         "trusted.glusterfs.0a118af0-3c20-4bdd-aded-694a17af6b5a.af95963b-bbe6-49cb-bf6d-db7260ea6f72.stime",
+///         key.to_string()
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum XattrKey {
+    /// `trusted.gfid`
+    Gfid,
+    /// `trusted.glusterfs.volume-id`
+    VolumeId,
+    /// `trusted.glusterfs.<mastervol_uuid>.xtime`
+    Xtime(VolumeId),
+    /// `trusted.glusterfs.<mastervol_uuid>.<slavevol_uuid>.stime`
+    Stime(VolumeId, VolumeId),
+    /// `trusted.glusterfs.<mastervol_uuid>.<slavevol_uuid>.entry_stime`
+    EntryStime(VolumeId, VolumeId),
+    /// `trusted.afr.<volname>-client-<N>`
+    AfrPending(String, u32),
+    /// `trusted.afr.dirty`
+    AfrDirty,
+    /// `trusted.glusterfs.dht`
+    DhtLayout,
+    /// `trusted.glusterfs.dht.linkto`
+    DhtLinkto,
+    /// `trusted.glusterfs.dht.commithash`
+    DhtCommitHash,
+    /// `trusted.glusterfs.dht.mds`
+    DhtMds,
+    /// `trusted.glusterfs.quota.size[.<version>]`, version 0 has no suffix
+    QuotaSize(u32),
+    /// `trusted.glusterfs.quota.<pgfid>.contri[.<version>]`
+    QuotaContri(Gfid, u32),
+    /// `trusted.glusterfs.quota.limit-set[.<version>]`
+    QuotaLimitSet(u32),
+    /// `trusted.glusterfs.quota.dirty`
+    QuotaDirty,
+    /// `trusted.glusterfs.shard.block-size`
+    ShardBlockSize,
+    /// `trusted.glusterfs.shard.file-size`
+    ShardFileSize,
+    /// `trusted.ec.version`
+    EcVersion,
+    /// `trusted.ec.size`
+    EcSize,
+    /// `trusted.ec.config`
+    EcConfig,
+    /// `trusted.ec.dirty`
+    EcDirty,
+    /// `trusted.bit-rot.signature`
+    BitrotSignature,
+    /// `trusted.bit-rot.version`
+    BitrotVersion,
+    /// `trusted.bit-rot.bad-file`
+    BitrotBadFile,
+    /// `trusted.gfid2path.<hash>`
+    Gfid2Path(String),
+}
+
+impl fmt::Display for XattrKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            XattrKey::Gfid => write!(f, "trusted.gfid"),
+            XattrKey::VolumeId => write!(f, "{}.volume-id", GLUSTERFS_PREFIX),
+            XattrKey::Xtime(ref m) => write!(f, "{}.{}.xtime", GLUSTERFS_PREFIX, m),
+            XattrKey::Stime(ref m, ref s) => write!(f, "{}.{}.{}.stime", GLUSTERFS_PREFIX, m, s),
+            XattrKey::EntryStime(ref m, ref s) => {
+                write!(f, "{}.{}.{}.entry_stime", GLUSTERFS_PREFIX, m, s)
+            }
+            XattrKey::AfrPending(ref vol, client) => {
+                write!(f, "{}.{}-client-{}", AFR_PREFIX, vol, client)
+            }
+            XattrKey::AfrDirty => write!(f, "{}.dirty", AFR_PREFIX),
+            XattrKey::DhtLayout => write!(f, "{}.dht", GLUSTERFS_PREFIX),
+            XattrKey::DhtLinkto => write!(f, "{}.dht.linkto", GLUSTERFS_PREFIX),
+            XattrKey::DhtCommitHash => write!(f, "{}.dht.commithash", GLUSTERFS_PREFIX),
+            XattrKey::DhtMds => write!(f, "{}.dht.mds", GLUSTERFS_PREFIX),
+            XattrKey::QuotaSize(ver) => {
+                write!(f, "{}.quota.size{}", GLUSTERFS_PREFIX, VersionSuffix(ver))
+            }
+            XattrKey::QuotaContri(ref pgfid, ver) => {
+                write!(f, "{}.quota.{}.contri{}", GLUSTERFS_PREFIX, pgfid, VersionSuffix(ver))
+            }
+            XattrKey::QuotaLimitSet(ver) => {
+                write!(f, "{}.quota.limit-set{}", GLUSTERFS_PREFIX, VersionSuffix(ver))
+            }
+            XattrKey::QuotaDirty => write!(f, "{}.quota.dirty", GLUSTERFS_PREFIX),
+            XattrKey::ShardBlockSize => write!(f, "{}.shard.block-size", GLUSTERFS_PREFIX),
+            XattrKey::ShardFileSize => write!(f, "{}.shard.file-size", GLUSTERFS_PREFIX),
+            XattrKey::EcVersion => write!(f, "{}.version", EC_PREFIX),
+            XattrKey::EcSize => write!(f, "{}.size", EC_PREFIX),
+            XattrKey::EcConfig => write!(f, "{}.config", EC_PREFIX),
+            XattrKey::EcDirty => write!(f, "{}.dirty", EC_PREFIX),
+            XattrKey::BitrotSignature => write!(f, "{}.signature", BITROT_PREFIX),
+            XattrKey::BitrotVersion => write!(f, "{}.version", BITROT_PREFIX),
+            XattrKey::BitrotBadFile => write!(f, "{}.bad-file", BITROT_PREFIX),
+            XattrKey::Gfid2Path(ref hash) => write!(f, "{}.{}", GFID2PATH_PREFIX, hash),
+        }
+    }
+}
+
+/// Quota xattr version suffix, empty for version 0
+struct VersionSuffix(u32);
+
+impl fmt::Display for VersionSuffix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            0 => Ok(()),
+            ver => write!(f, ".{}", ver),
+        }
+    }
+}
+
+impl FromStr for XattrKey {
+    type Err = GlusterXattrError;
+
+    fn from_str(s: &str) -> Result<XattrKey, GlusterXattrError> {
+        parse_key(s).ok_or_else(|| GlusterXattrError::UnknownXattr(s.to_string()))
+    }
+}
+
+fn parse_key(s: &str) -> Option<XattrKey> {
+    if s == "trusted.gfid" {
+        return Some(XattrKey::Gfid);
+    }
+
+    if let Some(rest) = strip_prefix(s, AFR_PREFIX) {
+        if rest == "dirty" {
+            return Some(XattrKey::AfrDirty);
+        }
+        let idx = rest.rfind("-client-")?;
+        let client = rest[idx + "-client-".len()..].parse().ok()?;
+        if idx == 0 {
+            return None;
+        }
+        return Some(XattrKey::AfrPending(rest[..idx].to_string(), client));
+    }
+
+    if let Some(rest) = strip_prefix(s, EC_PREFIX) {
+        return match rest {
+            "version" => Some(XattrKey::EcVersion),
+            "size" => Some(XattrKey::EcSize),
+            "config" => Some(XattrKey::EcConfig),
+            "dirty" => Some(XattrKey::EcDirty),
+            _ => None,
+        };
+    }
+
+    if let Some(rest) = strip_prefix(s, BITROT_PREFIX) {
+        return match rest {
+            "signature" => Some(XattrKey::BitrotSignature),
+            "version" => Some(XattrKey::BitrotVersion),
+            "bad-file" => Some(XattrKey::BitrotBadFile),
+            _ => None,
+        };
+    }
+
+    if let Some(hash) = strip_prefix(s, GFID2PATH_PREFIX) {
+        if hash.contains('.') {
+            return None;
+        }
+        return Some(XattrKey::Gfid2Path(hash.to_string()));
+    }
+
+    let rest = strip_prefix(s, GLUSTERFS_PREFIX)?;
+    match rest {
+        "volume-id" => return Some(XattrKey::VolumeId),
+        "dht" => return Some(XattrKey::DhtLayout),
+        "dht.linkto" => return Some(XattrKey::DhtLinkto),
+        "dht.commithash" => return Some(XattrKey::DhtCommitHash),
+        "dht.mds" => return Some(XattrKey::DhtMds),
+        "quota.dirty" => return Some(XattrKey::QuotaDirty),
+        "shard.block-size" => return Some(XattrKey::ShardBlockSize),
+        "shard.file-size" => return Some(XattrKey::ShardFileSize),
+        _ => {}
+    }
+
+    let parts: Vec<&str> = rest.split('.').collect();
+    match parts.as_slice() {
+        [m, "xtime"] => Some(XattrKey::Xtime(parse_volume_id(m)?)),
+        [m, s, "stime"] => Some(XattrKey::Stime(parse_volume_id(m)?, parse_volume_id(s)?)),
+        [m, s, "entry_stime"] => {
+            Some(XattrKey::EntryStime(parse_volume_id(m)?, parse_volume_id(s)?))
+        }
+        ["quota", "size"] => Some(XattrKey::QuotaSize(0)),
+        ["quota", "size", ver] => Some(XattrKey::QuotaSize(parse_version(ver)?)),
+        ["quota", "limit-set"] => Some(XattrKey::QuotaLimitSet(0)),
+        ["quota", "limit-set", ver] => Some(XattrKey::QuotaLimitSet(parse_version(ver)?)),
+        ["quota", pgfid, "contri"] => Some(XattrKey::QuotaContri(parse_gfid(pgfid)?, 0)),
+        ["quota", pgfid, "contri", ver] => {
+            Some(XattrKey::QuotaContri(parse_gfid(pgfid)?, parse_version(ver)?))
+        }
+        _ => None,
+    }
+}
+
+fn strip_prefix<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    if s.len() > prefix.len() && s.starts_with(prefix) && s.as_bytes()[prefix.len()] == b'.' {
+        Some(&s[prefix.len() + 1..])
+    } else {
+        None
+    }
+}
+
+fn parse_volume_id(s: &str) -> Option<VolumeId> {
+    s.parse().ok()
+}
+
+fn parse_gfid(s: &str) -> Option<Gfid> {
+    s.parse().ok()
+}
+
+/// Version suffix, `.0` is never used since version 0 has no suffix
+fn parse_version(s: &str) -> Option<u32> {
+    match s.parse() {
+        Ok(0) | Err(_) => None,
+        Ok(ver) => Some(ver),
+    }
+}
+
+#[test]
+fn test_xattr_key_round_trip() {
+    let m = "0a118af0-3c20-4bdd-aded-694a17af6b5a";
+    let s = "af95963b-bbe6-49cb-bf6d-db7260ea6f72";
+    let keys = vec![
+        "trusted.gfid".to_string(),
+        "trusted.glusterfs.volume-id".to_string(),
+        format!("trusted.glusterfs.{}.xtime", m),
+        format!("trusted.glusterfs.{}.{}.stime", m, s),
+        format!("trusted.glusterfs.{}.{}.entry_stime", m, s),
+        "trusted.afr.gv0-client-1".to_string(),
+        "trusted.afr.my-vol-client-12".to_string(),
+        "trusted.afr.dirty".to_string(),
+        "trusted.glusterfs.dht".to_string(),
+        "trusted.glusterfs.dht.linkto".to_string(),
+        "trusted.glusterfs.dht.commithash".to_string(),
+        "trusted.glusterfs.dht.mds".to_string(),
+        "trusted.glusterfs.quota.size".to_string(),
+        "trusted.glusterfs.quota.size.1".to_string(),
+        format!("trusted.glusterfs.quota.{}.contri", m),
+        format!("trusted.glusterfs.quota.{}.contri.1", m),
+        "trusted.glusterfs.quota.limit-set".to_string(),
+        "trusted.glusterfs.quota.limit-set.1".to_string(),
+        "trusted.glusterfs.quota.dirty".to_string(),
+        "trusted.glusterfs.shard.block-size".to_string(),
+        "trusted.glusterfs.shard.file-size".to_string(),
+        "trusted.ec.version".to_string(),
+        "trusted.ec.size".to_string(),
+        "trusted.ec.config".to_string(),
+        "trusted.ec.dirty".to_string(),
+        "trusted.bit-rot.signature".to_string(),
+        "trusted.bit-rot.version".to_string(),
+        "trusted.bit-rot.bad-file".to_string(),
+        "trusted.gfid2path.d16e15bafe6e4257".to_string(),
+    ];
+    for k in keys {
+        let key: XattrKey = k.parse().unwrap();
+        assert_eq!(k, key.to_string());
+    }
+
+    assert_eq!(
+        XattrKey::AfrPending("my-vol".to_string(), 12),
+        "trusted.afr.my-vol-client-12".parse().unwrap()
+    );
+    assert_eq!(XattrKey::QuotaSize(2), "trusted.glusterfs.quota.size.2".parse().unwrap());
+    assert!("trusted.glusterfs.quota.size.0".parse::<XattrKey>().is_err());
+    assert!("trusted.glusterfs.xtime".parse::<XattrKey>().is_err());
+    assert!("trusted.afr.gv0-client-x".parse::<XattrKey>().is_err());
+    match "user.gfid".parse::<XattrKey>() {
+        Err(GlusterXattrError::UnknownXattr(name)) => assert_eq!("user.gfid", name),
+        other => panic!("unexpected {:?}", other),
+    }
+}
@@ -1,45 +1,132 @@
 extern crate xattr;
 extern crate byteorder;
 extern crate uuid;
+extern crate libc;
+extern crate sha2;
+extern crate xxhash_rust;
 
+mod afr;
+mod bitrot;
+mod dht;
+mod ec;
+mod error;
+mod fd;
+mod gfid;
+mod gfid2path;
+mod handle;
+mod key;
+mod quota;
+mod shard;
+mod splitbrain;
+mod target;
+#[cfg(test)]
+mod testutil;
+mod walk;
+mod xtime;
+
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
-use std::io::{Cursor, Error};
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
-#[derive(Debug)]
-pub struct Xtime(u32, u32);
+pub use afr::{get_afr_dirty, get_afr_pending, list_afr_keys, set_afr_pending, AfrChangelog};
+pub use dht::{audit_commit_hash, check_layout, dht_hash, diff_layouts, dm_hash, find_hashed_subvol, find_stale_linkto_files,
+              fix_layout, get_dht_commit_hash, get_dht_layout, get_dht_linkto, get_dht_mds, hashed_subvol, is_linkto_file,
+              plan_layout, set_dht_commit_hash, set_dht_layout, set_dht_linkto, set_dht_mds, CommitHashMismatch, DhtLayout,
+              HashRange, LayoutChange, LayoutOverlap, LayoutReport, StaleLinkto, StaleLinktoReason, DHT_LAYOUT_HASH_INVALID};
+pub use ec::{check_disperse, get_ec_config, get_ec_dirty, get_ec_size, get_ec_version, set_ec_config, set_ec_dirty, set_ec_size,
+             set_ec_version, EcConfig, EcDirty, EcFragment, EcReport, EcVersion};
+pub use bitrot::{clear_bitrot_bad_file, get_bitrot_signature, get_bitrot_version, is_bitrot_bad_file, list_bitrot_bad_files,
+                 scrub_brick, scrub_file, BitrotSignature, BitrotVersion, ScrubOptions, ScrubReport, ScrubStatus,
+                 BITROT_HASH_SHA256};
+pub use error::{GlusterXattrError, Result};
+pub use fd::FileXattrExt;
+pub use gfid::{Gfid, VolumeId};
+pub use gfid2path::{gfid2path_hash, list_gfid2path, resolve_gfid_paths, Gfid2Path};
+pub use handle::{audit_handles, handle_path, open_by_gfid, resolve_dir_path, HandleFix, HandleIssue, HandleIssueKind,
+                 HANDLE_DIR};
+pub use key::XattrKey;
+pub use quota::{get_quota_contri, get_quota_dirty, get_quota_limit, get_quota_size, quota_fsck, set_quota_contri,
+                set_quota_dirty, set_quota_limit, set_quota_size, QuotaLimit, QuotaMismatch, QuotaMismatchKind, QuotaSize};
+pub use shard::{check_shards, get_shard_block_size, get_shard_file_size, reassemble_shards, set_shard_block_size,
+                set_shard_file_size, shard_count, shard_path, InvalidShard, ReassembleReport, ShardFileSize, ShardProblem,
+                ShardReport, SHARD_DIR};
+pub use splitbrain::{analyze_replica, resolve_split_brain, AfrWrite, BrickGfid, HealSource, HealState, ReplicaReport, ReplicaSet,
+                     ReplicaStatus, SplitBrainType};
+pub use target::{FollowSymlinks, XattrTarget};
+pub use xtime::Xtime;
+
+fn get_xattr<P: XattrTarget + ?Sized> (path: &P, xattr_name: &str) -> Result<Vec<u8>> {
+    match path.get_raw(xattr_name) {
+        Ok(Some(v)) => Ok(v),
+        Ok(None) => Err(GlusterXattrError::XattrMissing(xattr_name.to_string())),
+        Err(e) => Err(GlusterXattrError::from_io(e, xattr_name)),
+    }
+}
+
+fn set_xattr<P: XattrTarget + ?Sized> (path: &P, xattr_name: &str, value: &[u8]) -> Result<()> {
+    path.set_raw(xattr_name, value).map_err(|e| GlusterXattrError::from_io(e, xattr_name))
+}
+
+fn remove_xattr<P: XattrTarget + ?Sized> (path: &P, xattr_name: &str) -> Result<()> {
+    path.remove_raw(xattr_name).map_err(|e| GlusterXattrError::from_io(e, xattr_name))
+}
+
+/// Path of `rel` inside the brick, `rel` is relative to the brick root
+/// with or without the leading `/`
+fn brick_join<B: AsRef<Path>, P: AsRef<Path>> (brick: B, rel: P) -> PathBuf {
+    let rel = rel.as_ref();
+    brick.as_ref().join(rel.strip_prefix("/").unwrap_or(rel))
+}
 
-const BRICK_GFID_XATTR: &'static str = "trusted.gfid";
-const VOLUME_ID_XATTR: &'static str = "trusted.glusterfs.volume-id";
-const XTIME_STIME_XATTR_PREFIX: &'static str = "trusted.glusterfs";
+/// Treat a missing xattr as `None`
+fn ignore_missing<T> (res: Result<T>) -> Result<Option<T>> {
+    match res {
+        Ok(v) => Ok(Some(v)),
+        Err(GlusterXattrError::XattrMissing(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
 
-fn get_xtime_stime (path: &str, xattr_name: &str) -> Result<Xtime, Error> {
-    let v = try!(xattr::get(path, xattr_name));
+fn check_len (xattr_name: &str, value: &[u8], expected: usize) -> Result<()> {
+    if value.len() != expected {
+        return Err(GlusterXattrError::Malformed {
+            name: xattr_name.to_string(),
+            expected,
+            actual: value.len(),
+        });
+    }
+    Ok(())
+}
 
-    let mut rdr = Cursor::new(v);
-    Ok(Xtime(rdr.read_u32::<BigEndian>().unwrap_or(0),
-             rdr.read_u32::<BigEndian>().unwrap_or(0)))
+fn get_xtime_stime<P: XattrTarget + ?Sized> (path: &P, xattr_name: &str) -> Result<Xtime> {
+    let v = get_xattr(path, xattr_name)?;
+    Xtime::decode(xattr_name, &v)
 }
 
 
-fn set_xtime_stime (path: &str, xattr_name: &str, sec: u32, msec: u32) -> Result<(), Error> {
-    let mut wtr = vec![];
-    try!(wtr.write_u32::<BigEndian>(sec));
-    try!(wtr.write_u32::<BigEndian>(msec));
-    xattr::set(path, xattr_name, &wtr)
+fn set_xtime_stime<P: XattrTarget + ?Sized> (path: &P, xattr_name: &str, sec: u32, usec: u32) -> Result<()> {
+    set_xattr(path, xattr_name, &Xtime(sec, usec).encode())
 }
 
 
-fn get_uuid (path: &str, xattr_name: &str) -> Result<String, Error> {
-    let v = try!(xattr::get(path, xattr_name));
-    let uuid = Uuid::from_bytes(&v);
-    Ok(uuid.unwrap().hyphenated().to_string())
+fn get_uuid_bytes<P: XattrTarget + ?Sized> (path: &P, xattr_name: &str) -> Result<[u8; 16]> {
+    let v = get_xattr(path, xattr_name)?;
+    check_len(xattr_name, &v, 16)?;
+
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&v);
+    Ok(bytes)
 }
 
 
-fn set_uuid (path: &str, xattr_name: &str, gfid: &str) -> Result<(), Error> {
-    let uuid = Uuid::parse_str(gfid).unwrap();
-    xattr::set(path, xattr_name, uuid.as_bytes())
+fn get_uuid<P: XattrTarget + ?Sized> (path: &P, xattr_name: &str) -> Result<String> {
+    let bytes = get_uuid_bytes(path, xattr_name)?;
+    Ok(Uuid::from_bytes(bytes).hyphenated().to_string())
+}
+
+
+fn set_uuid<P: XattrTarget + ?Sized> (path: &P, xattr_name: &str, gfid: &str) -> Result<()> {
+    let uuid = Uuid::parse_str(gfid).map_err(|_| GlusterXattrError::InvalidUuid(gfid.to_string()))?;
+    set_xattr(path, xattr_name, uuid.as_bytes())
 }
 
 /// Get GFID(`trusted.gfid`)
@@ -59,8 +146,8 @@ fn set_uuid (path: &str, xattr_name: &str, gfid: &str) -> Result<(), Error> {
 ///     }
 /// }
 /// ```
-pub fn get_gfid (path: &str) -> Result<String, Error> {
-    get_uuid(path, BRICK_GFID_XATTR)
+pub fn get_gfid<P: XattrTarget> (path: P) -> Result<String> {
+    get_uuid(&path, &XattrKey::Gfid.to_string())
 }
 
 /// Set GFID(`trusted.gfid`)
@@ -80,8 +167,8 @@ pub fn get_gfid (path: &str) -> Result<String, Error> {
 ///     }
 /// }
 /// ```
-pub fn set_gfid (path: &str, gfid: &str) -> Result<(), Error> {
-    set_uuid(path, BRICK_GFID_XATTR, gfid)
+pub fn set_gfid<P: XattrTarget> (path: P, gfid: &str) -> Result<()> {
+    set_uuid(&path, &XattrKey::Gfid.to_string(), gfid)
 }
 
 /// Get Volume ID(`trusted.glusterfs.volume-id`)
@@ -101,8 +188,8 @@ pub fn set_gfid (path: &str, gfid: &str) -> Result<(), Error> {
 ///     }
 /// }
 /// ```
-pub fn get_volume_id (path: &str) -> Result<String, Error> {
-    get_uuid(path, VOLUME_ID_XATTR)
+pub fn get_volume_id<P: XattrTarget> (path: P) -> Result<String> {
+    get_uuid(&path, &XattrKey::VolumeId.to_string())
 }
 
 /// Set Volume ID(`trusted.glusterfs.volume-id`)
@@ -122,8 +209,94 @@ pub fn get_volume_id (path: &str) -> Result<String, Error> {
 ///     }
 /// }
 /// ```
-pub fn set_volume_id (path: &str, volume_id: &str) -> Result<(), Error> {
-    set_uuid(path, VOLUME_ID_XATTR, volume_id)
+pub fn set_volume_id<P: XattrTarget> (path: P, volume_id: &str) -> Result<()> {
+    set_uuid(&path, &XattrKey::VolumeId.to_string(), volume_id)
+}
+
+/// Get GFID(`trusted.gfid`) as `Gfid`
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_gfid_typed;
+///
+/// fn main() {
+///     let res = get_gfid_typed("/bricks/b1/f1");
+///     match res {
+///         Ok(v) => println!("GFID: {}, root: {}", v, v.is_root()),
+///         Err(e) => println!("Failed to get GFID: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_gfid_typed<P: XattrTarget> (path: P) -> Result<Gfid> {
+    get_uuid_bytes(&path, &XattrKey::Gfid.to_string()).map(Gfid::from_bytes)
+}
+
+/// Set GFID(`trusted.gfid`) from `Gfid`
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{set_gfid_typed, Gfid};
+///
+/// fn main() {
+///     let gfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+///     let res = set_gfid_typed("/bricks/b1/f1", &gfid);
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set GFID: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_gfid_typed<P: XattrTarget> (path: P, gfid: &Gfid) -> Result<()> {
+    set_xattr(&path, &XattrKey::Gfid.to_string(), gfid.as_bytes())
+}
+
+/// Get Volume ID(`trusted.glusterfs.volume-id`) as `VolumeId`
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_volume_id_typed;
+///
+/// fn main() {
+///     let res = get_volume_id_typed("/bricks/b1");
+///     match res {
+///         Ok(v) => println!("Volume ID: {}", v),
+///         Err(e) => println!("Failed to get Volume ID: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_volume_id_typed<P: XattrTarget> (path: P) -> Result<VolumeId> {
+    get_uuid_bytes(&path, &XattrKey::VolumeId.to_string()).map(VolumeId::from_bytes)
+}
+
+/// Set Volume ID(`trusted.glusterfs.volume-id`) from `VolumeId`
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{set_volume_id_typed, VolumeId};
+///
+/// fn main() {
+///     let volume_id: VolumeId = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+///     let res = set_volume_id_typed("/bricks/b1", &volume_id);
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set volume ID: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_volume_id_typed<P: XattrTarget> (path: P, volume_id: &VolumeId) -> Result<()> {
+    set_xattr(&path, &XattrKey::VolumeId.to_string(), volume_id.as_bytes())
 }
 
 /// Get Xtime(`trusted.glusterfs.<mastervol_uuid>.xtime`)
@@ -143,10 +316,9 @@ pub fn set_volume_id (path: &str, volume_id: &str) -> Result<(), Error> {
 ///     }
 /// }
 /// ```
-pub fn get_xtime (path: &str, volume_id: &str) -> Result<Xtime, Error> {
-    let xattr_name = format!("{}.{}.xtime", XTIME_STIME_XATTR_PREFIX, volume_id);
-    let xattr_name = xattr_name.as_str();
-    get_xtime_stime (path, xattr_name)
+pub fn get_xtime<P: XattrTarget> (path: P, volume_id: &str) -> Result<Xtime> {
+    let key = XattrKey::Xtime(volume_id.parse()?);
+    get_xtime_stime(&path, &key.to_string())
 }
 
 /// Set Xtime(`trusted.glusterfs.<mastervol_uuid>.xtime`)
@@ -167,10 +339,9 @@ pub fn get_xtime (path: &str, volume_id: &str) -> Result<Xtime, Error> {
 ///     }
 /// }
 /// ```
-pub fn set_xtime (path: &str, volume_id: &str, sec: u32, msec: u32) -> Result<(), Error> {
-    let xattr_name = format!("{}.{}.xtime", XTIME_STIME_XATTR_PREFIX, volume_id);
-    let xattr_name = xattr_name.as_str();
-    set_xtime_stime (path, xattr_name, sec, msec)
+pub fn set_xtime<P: XattrTarget> (path: P, volume_id: &str, sec: u32, usec: u32) -> Result<()> {
+    let key = XattrKey::Xtime(volume_id.parse()?);
+    set_xtime_stime(&path, &key.to_string(), sec, usec)
 }
 
 /// Get Stime(`trusted.glusterfs.<mastervol_uuid>.<slavevol_uuid>.stime`)
@@ -191,10 +362,9 @@ pub fn set_xtime (path: &str, volume_id: &str, sec: u32, msec: u32) -> Result<()
 ///     }
 /// }
 /// ```
-pub fn get_stime (path: &str, master_volume_id: &str, slave_volume_id: &str) -> Result<Xtime, Error> {
-    let xattr_name = format!("{}.{}.{}.stime", XTIME_STIME_XATTR_PREFIX, master_volume_id, slave_volume_id);
-    let xattr_name = xattr_name.as_str();
-    get_xtime(path, xattr_name)
+pub fn get_stime<P: XattrTarget> (path: P, master_volume_id: &str, slave_volume_id: &str) -> Result<Xtime> {
+    let key = XattrKey::Stime(master_volume_id.parse()?, slave_volume_id.parse()?);
+    get_xtime_stime(&path, &key.to_string())
 }
 
 /// Set Stime(`trusted.glusterfs.<mastervol_uuid>.<slavevol_uuid>.stime`)
@@ -216,10 +386,9 @@ pub fn get_stime (path: &str, master_volume_id: &str, slave_volume_id: &str) ->
 ///     }
 /// }
 /// ```
-pub fn set_stime(path: &str, master_volume_id: &str, slave_volume_id: &str, sec: u32, msec: u32) -> Result<(), Error> {
-    let xattr_name = format!("{}.{}.{}.stime", XTIME_STIME_XATTR_PREFIX, master_volume_id, slave_volume_id);
-    let xattr_name = xattr_name.as_str();
-    set_xtime(path, xattr_name, sec, msec)
+pub fn set_stime<P: XattrTarget> (path: P, master_volume_id: &str, slave_volume_id: &str, sec: u32, usec: u32) -> Result<()> {
+    let key = XattrKey::Stime(master_volume_id.parse()?, slave_volume_id.parse()?);
+    set_xtime_stime(&path, &key.to_string(), sec, usec)
 }
 
 
@@ -237,3 +406,47 @@ fn test_set_and_get_uuid(){
     let val = get_uuid("./testfile", "user.gfid").unwrap();
     assert_eq!("bb74c663-2552-41aa-a0ae-d4d94d9dd187", val);
 }
+
+#[test]
+fn test_set_and_get_typed_gfid() {
+    let gfid: Gfid = "5e3ac1f8-6f1e-4c06-8a9d-0e3a8d9f61a2".parse().unwrap();
+    assert_eq!((), set_xattr("./testfile", "user.typed.gfid", gfid.as_bytes()).unwrap());
+    let val = get_uuid_bytes("./testfile", "user.typed.gfid").map(Gfid::from_bytes).unwrap();
+    assert_eq!(gfid, val);
+}
+
+#[test]
+fn test_symlink_follow_and_nofollow() {
+    use std::os::unix::fs::symlink;
+
+    let dir = testutil::TempDir::new("symlink");
+    let file = dir.join("testfile");
+    let link = dir.join("testfile.symlink");
+    std::fs::File::create(&file).unwrap();
+    symlink("testfile", &link).unwrap();
+    assert_eq!((), set_xtime_stime(&file, "user.glusterfs.symlink.xtime", 10, 1).unwrap());
+
+    let val = get_xtime_stime(&FollowSymlinks(&link), "user.glusterfs.symlink.xtime").unwrap();
+    assert_eq!(Xtime(10, 1), val);
+    match get_xtime_stime(&link, "user.glusterfs.symlink.xtime") {
+        Err(GlusterXattrError::XattrMissing(_)) => {},
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_malformed_and_missing_xattr() {
+    assert_eq!((), set_xattr("./testfile", "user.glusterfs.short", &[1, 2, 3]).unwrap());
+    match get_uuid("./testfile", "user.glusterfs.short") {
+        Err(GlusterXattrError::Malformed { expected: 16, actual: 3, .. }) => {},
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match get_xtime_stime("./testfile", "user.glusterfs.notset.xtime") {
+        Err(GlusterXattrError::XattrMissing(_)) => {},
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match set_uuid("./testfile", "user.gfid", "not-a-uuid") {
+        Err(GlusterXattrError::InvalidUuid(_)) => {},
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
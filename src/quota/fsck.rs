@@ -0,0 +1,157 @@
+use std::fs;
+use std::ops::AddAssign;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use super::{get_quota_contri, get_quota_size, set_quota_contri, set_quota_size, QuotaSize};
+use error::Result;
+use gfid::Gfid;
+
+impl AddAssign for QuotaSize {
+    fn add_assign(&mut self, other: QuotaSize) {
+        self.size += other.size;
+        self.file_count += other.file_count;
+        self.dir_count += other.dir_count;
+    }
+}
+
+/// Which quota xattr does not match the computed value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuotaMismatchKind {
+    /// `quota.size` of a directory
+    Size,
+    /// `quota.<pgfid>.contri` of a file or directory to its parent
+    Contri(Gfid),
+}
+
+/// Quota xattr whose value does not match the accounting recomputed
+/// from the brick
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuotaMismatch {
+    pub path: PathBuf,
+    pub kind: QuotaMismatchKind,
+    /// `None` if the xattr is not set
+    pub stored: Option<QuotaSize>,
+    pub computed: QuotaSize,
+}
+
+struct Fsck {
+    brick_root: PathBuf,
+    version: u32,
+    repair: bool,
+    mismatches: Vec<QuotaMismatch>,
+}
+
+/// Quota accounts the disk usage, not the file size
+fn disk_usage(meta: &fs::Metadata) -> i64 {
+    meta.blocks() as i64 * 512
+}
+
+impl Fsck {
+    fn check(&mut self, path: &Path, kind: QuotaMismatchKind, computed: QuotaSize) -> Result<()> {
+        let stored = match kind {
+            QuotaMismatchKind::Size => ::ignore_missing(get_quota_size(path, self.version))?,
+            QuotaMismatchKind::Contri(ref pgfid) => ::ignore_missing(get_quota_contri(path, pgfid, self.version))?,
+        };
+        if stored == Some(computed) {
+            return Ok(());
+        }
+
+        if self.repair {
+            match kind {
+                QuotaMismatchKind::Size => set_quota_size(path, self.version, &computed)?,
+                QuotaMismatchKind::Contri(ref pgfid) => set_quota_contri(path, pgfid, self.version, &computed)?,
+            }
+        }
+        self.mismatches.push(QuotaMismatch { path: path.to_path_buf(), kind, stored, computed });
+        Ok(())
+    }
+
+    /// Check the directory bottom-up and return its computed size
+    fn check_dir(&mut self, dir: &Path, gfid: &Gfid) -> Result<QuotaSize> {
+        let meta = fs::symlink_metadata(dir)?;
+        let mut total = QuotaSize::new(disk_usage(&meta), 0, 1);
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if dir == self.brick_root && entry.file_name() == ".glusterfs" {
+                continue;
+            }
+
+            let meta = fs::symlink_metadata(&path)?;
+            let contri = if meta.is_dir() {
+                let child_gfid = ::get_gfid_typed(&path)?;
+                self.check_dir(&path, &child_gfid)?
+            } else if ::is_linkto_file(&path)? {
+                continue;
+            } else {
+                QuotaSize::new(disk_usage(&meta), 1, 0)
+            };
+
+            self.check(&path, QuotaMismatchKind::Contri(*gfid), contri)?;
+            total += contri;
+        }
+
+        self.check(dir, QuotaMismatchKind::Size, total)?;
+        Ok(total)
+    }
+}
+
+/// Recompute the quota accounting(size, file count and directory count)
+/// of the brick bottom-up and compare it with the `quota.size` of every
+/// directory and the `quota.<pgfid>.contri` of every file and directory
+///
+/// With `repair` the mismatching xattrs are overwritten with the
+/// computed values. DHT linkto files are not accounted, like the quota
+/// marker does. Should be run while the brick is offline.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::quota_fsck;
+///
+/// fn main() {
+///     match quota_fsck("/bricks/b1", 1, false) {
+///         Ok(mismatches) => for m in mismatches {
+///             println!("{}: {:?} stored={:?} computed={:?}", m.path.display(), m.kind, m.stored, m.computed)
+///         },
+///         Err(e) => println!("Failed to verify quota: {}", e)
+///     }
+/// }
+/// ```
+pub fn quota_fsck<B: AsRef<Path>>(brick_root: B, version: u32, repair: bool) -> Result<Vec<QuotaMismatch>> {
+    let mut fsck = Fsck {
+        brick_root: brick_root.as_ref().to_path_buf(),
+        version,
+        repair,
+        mismatches: vec![],
+    };
+    let root = fsck.brick_root.clone();
+    fsck.check_dir(&root, &Gfid::ROOT)?;
+    Ok(fsck.mismatches)
+}
+
+#[test]
+fn test_quota_fsck_without_xattrs() {
+    let root = ::testutil::TempDir::new("quota");
+    fs::create_dir_all(root.join(".glusterfs")).unwrap();
+    fs::write(root.join("f1"), vec![1; 8192]).unwrap();
+    fs::write(root.join("f2"), b"hello").unwrap();
+
+    let mismatches = quota_fsck(&root, 1, false).unwrap();
+
+    assert_eq!(3, mismatches.len());
+    assert!(mismatches.iter().all(|m| m.stored.is_none()));
+    let size = mismatches.iter().find(|m| m.kind == QuotaMismatchKind::Size).unwrap();
+    assert_eq!(&*root, size.path);
+    assert_eq!((2, 1), (size.computed.file_count, size.computed.dir_count));
+    let contri: i64 = mismatches
+        .iter()
+        .filter(|m| m.kind == QuotaMismatchKind::Contri(Gfid::ROOT))
+        .map(|m| m.computed.size)
+        .sum();
+    assert!(contri >= 8192 && contri < size.computed.size);
+}
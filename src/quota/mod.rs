@@ -0,0 +1,284 @@
+//! Quota xattrs
+//!
+//! Size and contribution xattrs carry the quota version as suffix
+//! (`trusted.glusterfs.quota.size.1`), version 0 has no suffix.
+
+use byteorder::{BigEndian, ByteOrder};
+
+use error::{GlusterXattrError, Result};
+use gfid::Gfid;
+use key::XattrKey;
+use target::XattrTarget;
+
+mod fsck;
+
+pub use self::fsck::{quota_fsck, QuotaMismatch, QuotaMismatchKind};
+
+/// Size, file count and directory count accounted by quota, used for
+/// both `quota.size` and `quota.<pgfid>.contri`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct QuotaSize {
+    pub size: i64,
+    pub file_count: i64,
+    pub dir_count: i64,
+}
+
+impl QuotaSize {
+    pub fn new(size: i64, file_count: i64, dir_count: i64) -> QuotaSize {
+        QuotaSize { size, file_count, dir_count }
+    }
+
+    /// Decode 24 byte value, or 8 byte value(size only) written by
+    /// older versions
+    pub fn decode(xattr_name: &str, value: &[u8]) -> Result<QuotaSize> {
+        match value.len() {
+            8 => Ok(QuotaSize::new(BigEndian::read_i64(value), 0, 0)),
+            24 => Ok(QuotaSize::new(
+                BigEndian::read_i64(&value[0..8]),
+                BigEndian::read_i64(&value[8..16]),
+                BigEndian::read_i64(&value[16..24]),
+            )),
+            len => Err(GlusterXattrError::Malformed {
+                name: xattr_name.to_string(),
+                expected: 24,
+                actual: len,
+            }),
+        }
+    }
+
+    pub fn encode(&self) -> [u8; 24] {
+        let mut buf = [0; 24];
+        BigEndian::write_i64(&mut buf[0..8], self.size);
+        BigEndian::write_i64(&mut buf[8..16], self.file_count);
+        BigEndian::write_i64(&mut buf[16..24], self.dir_count);
+        buf
+    }
+}
+
+/// Usage limit of a directory(`quota.limit-set`), soft limit is the
+/// percentage of the hard limit, -1 to use the volume default
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct QuotaLimit {
+    pub hard_limit: i64,
+    pub soft_limit_percent: i64,
+}
+
+impl QuotaLimit {
+    pub fn new(hard_limit: i64, soft_limit_percent: i64) -> QuotaLimit {
+        QuotaLimit { hard_limit, soft_limit_percent }
+    }
+
+    pub fn decode(xattr_name: &str, value: &[u8]) -> Result<QuotaLimit> {
+        ::check_len(xattr_name, value, 16)?;
+        Ok(QuotaLimit::new(BigEndian::read_i64(&value[0..8]), BigEndian::read_i64(&value[8..16])))
+    }
+
+    pub fn encode(&self) -> [u8; 16] {
+        let mut buf = [0; 16];
+        BigEndian::write_i64(&mut buf[0..8], self.hard_limit);
+        BigEndian::write_i64(&mut buf[8..16], self.soft_limit_percent);
+        buf
+    }
+}
+
+/// Get quota size(`trusted.glusterfs.quota.size[.<version>]`) of a directory
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_quota_size;
+///
+/// fn main() {
+///     let res = get_quota_size("/bricks/b1/dir1", 1);
+///     match res {
+///         Ok(v) => println!("Size: {}, files: {}, dirs: {}", v.size, v.file_count, v.dir_count),
+///         Err(e) => println!("Failed to get quota size: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_quota_size<P: XattrTarget>(path: P, version: u32) -> Result<QuotaSize> {
+    let key = XattrKey::QuotaSize(version).to_string();
+    let v = ::get_xattr(&path, &key)?;
+    QuotaSize::decode(&key, &v)
+}
+
+/// Set quota size(`trusted.glusterfs.quota.size[.<version>]`) of a directory
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{set_quota_size, QuotaSize};
+///
+/// fn main() {
+///     let res = set_quota_size("/bricks/b1/dir1", 1, &QuotaSize::new(4096, 1, 1));
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set quota size: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_quota_size<P: XattrTarget>(path: P, version: u32, size: &QuotaSize) -> Result<()> {
+    ::set_xattr(&path, &XattrKey::QuotaSize(version).to_string(), &size.encode())
+}
+
+/// Get contribution(`trusted.glusterfs.quota.<pgfid>.contri[.<version>]`)
+/// of a file or directory to its parent
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{get_gfid_typed, get_quota_contri};
+///
+/// fn main() {
+///     let res = get_gfid_typed("/bricks/b1/dir1")
+///         .and_then(|pgfid| get_quota_contri("/bricks/b1/dir1/f1", &pgfid, 1));
+///     match res {
+///         Ok(v) => println!("Contribution: {:?}", v),
+///         Err(e) => println!("Failed to get contribution: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_quota_contri<P: XattrTarget>(path: P, pgfid: &Gfid, version: u32) -> Result<QuotaSize> {
+    let key = XattrKey::QuotaContri(*pgfid, version).to_string();
+    let v = ::get_xattr(&path, &key)?;
+    QuotaSize::decode(&key, &v)
+}
+
+/// Set contribution(`trusted.glusterfs.quota.<pgfid>.contri[.<version>]`)
+/// of a file or directory to its parent
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{set_quota_contri, Gfid, QuotaSize};
+///
+/// fn main() {
+///     let res = set_quota_contri("/bricks/b1/dir1/f1", &Gfid::ROOT, 1, &QuotaSize::new(512, 1, 0));
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set contribution: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_quota_contri<P: XattrTarget>(path: P, pgfid: &Gfid, version: u32, contri: &QuotaSize) -> Result<()> {
+    ::set_xattr(&path, &XattrKey::QuotaContri(*pgfid, version).to_string(), &contri.encode())
+}
+
+/// Get usage limit(`trusted.glusterfs.quota.limit-set[.<version>]`) of a directory
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_quota_limit;
+///
+/// fn main() {
+///     let res = get_quota_limit("/bricks/b1/dir1", 0);
+///     match res {
+///         Ok(v) => println!("Hard limit: {}, soft limit: {}%", v.hard_limit, v.soft_limit_percent),
+///         Err(e) => println!("Failed to get quota limit: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_quota_limit<P: XattrTarget>(path: P, version: u32) -> Result<QuotaLimit> {
+    let key = XattrKey::QuotaLimitSet(version).to_string();
+    let v = ::get_xattr(&path, &key)?;
+    QuotaLimit::decode(&key, &v)
+}
+
+/// Set usage limit(`trusted.glusterfs.quota.limit-set[.<version>]`) of a directory
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{set_quota_limit, QuotaLimit};
+///
+/// fn main() {
+///     let res = set_quota_limit("/bricks/b1/dir1", 0, &QuotaLimit::new(10737418240, 80));
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set quota limit: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_quota_limit<P: XattrTarget>(path: P, version: u32, limit: &QuotaLimit) -> Result<()> {
+    ::set_xattr(&path, &XattrKey::QuotaLimitSet(version).to_string(), &limit.encode())
+}
+
+/// Get quota dirty flag(`trusted.glusterfs.quota.dirty`), set while the
+/// size of a directory is being updated
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_quota_dirty;
+///
+/// fn main() {
+///     let res = get_quota_dirty("/bricks/b1/dir1");
+///     match res {
+///         Ok(v) => println!("Dirty: {}", v),
+///         Err(e) => println!("Failed to get quota dirty: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_quota_dirty<P: XattrTarget>(path: P) -> Result<bool> {
+    let v = ::get_xattr(&path, &XattrKey::QuotaDirty.to_string())?;
+    Ok(decode_dirty(&v))
+}
+
+/// Set quota dirty flag(`trusted.glusterfs.quota.dirty`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::set_quota_dirty;
+///
+/// fn main() {
+///     let res = set_quota_dirty("/bricks/b1/dir1", true);
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set quota dirty: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_quota_dirty<P: XattrTarget>(path: P, dirty: bool) -> Result<()> {
+    let v = if dirty { b"1\0" } else { b"0\0" };
+    ::set_xattr(&path, &XattrKey::QuotaDirty.to_string(), v)
+}
+
+/// Dirty flag is stored as the string "1" or "0"
+fn decode_dirty(value: &[u8]) -> bool {
+    match value.first() {
+        Some(&b'0') | Some(&0) | None => false,
+        Some(_) => true,
+    }
+}
+
+#[test]
+fn test_quota_size_encode_decode() {
+    let size = QuotaSize::new(1048576, 10, 2);
+    assert_eq!(size, QuotaSize::decode("x", &size.encode()).unwrap());
+    assert_eq!(QuotaSize::new(512, 0, 0), QuotaSize::decode("x", &[0, 0, 0, 0, 0, 0, 2, 0]).unwrap());
+    assert!(QuotaSize::decode("x", &[0; 16]).is_err());
+
+    let limit = QuotaLimit::new(10737418240, -1);
+    assert_eq!(limit, QuotaLimit::decode("x", &limit.encode()).unwrap());
+
+    assert!(decode_dirty(b"1\0"));
+    assert!(!decode_dirty(b"0\0"));
+}
@@ -0,0 +1,256 @@
+//! Sharding xattrs
+//!
+//! A sharded file is split into pieces of `block-size` bytes, the first
+//! piece is the base file and the piece `N` is `.shard/<gfid>.<N>` at the
+//! brick root, where `<gfid>` is the GFID of the base file. Only the base
+//! file carries the shard xattrs.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use error::Result;
+use gfid::Gfid;
+use key::XattrKey;
+use target::XattrTarget;
+
+mod reassemble;
+
+pub use self::reassemble::{reassemble_shards, InvalidShard, ReassembleReport, ShardProblem};
+
+/// Directory of the shards at the brick root
+pub const SHARD_DIR: &str = ".shard";
+
+/// Aggregated size of a sharded file(`trusted.glusterfs.shard.file-size`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ShardFileSize {
+    pub size: u64,
+    /// Number of 512 byte blocks allocated for all the shards
+    pub block_count: u64,
+}
+
+impl ShardFileSize {
+    pub fn new(size: u64, block_count: u64) -> ShardFileSize {
+        ShardFileSize { size, block_count }
+    }
+
+    /// Decode four u64s: size, unused, block count and unused
+    pub fn decode(xattr_name: &str, value: &[u8]) -> Result<ShardFileSize> {
+        ::check_len(xattr_name, value, 32)?;
+        Ok(ShardFileSize::new(BigEndian::read_u64(&value[0..8]), BigEndian::read_u64(&value[16..24])))
+    }
+
+    pub fn encode(&self) -> [u8; 32] {
+        let mut buf = [0; 32];
+        BigEndian::write_u64(&mut buf[0..8], self.size);
+        BigEndian::write_u64(&mut buf[16..24], self.block_count);
+        buf
+    }
+}
+
+/// Number of pieces(including the base file) of a file of `file_size`
+/// bytes, an empty file still has the base file
+pub fn shard_count(file_size: u64, block_size: u64) -> u64 {
+    if block_size == 0 {
+        return 1;
+    }
+    file_size.div_ceil(block_size).max(1)
+}
+
+/// Path of the shard `index`(1 or more) of the file `gfid` on the brick
+pub fn shard_path<B: AsRef<Path>>(brick_root: B, gfid: &Gfid, index: u64) -> PathBuf {
+    brick_root.as_ref().join(SHARD_DIR).join(format!("{}.{}", gfid, index))
+}
+
+/// Shards of a file found on a brick, indexes are sorted
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShardReport {
+    pub present: Vec<u64>,
+    /// Expected from the file size but not on the brick. Holes of sparse
+    /// files are never written and also show up here.
+    pub missing: Vec<u64>,
+    /// Present on the brick but beyond the file size
+    pub extra: Vec<u64>,
+}
+
+/// Get shard block size(`trusted.glusterfs.shard.block-size`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_shard_block_size;
+///
+/// fn main() {
+///     let res = get_shard_block_size("/bricks/b1/vm1.img");
+///     match res {
+///         Ok(v) => println!("Block size: {}", v),
+///         Err(e) => println!("Failed to get shard block size: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_shard_block_size<P: XattrTarget>(path: P) -> Result<u64> {
+    let key = XattrKey::ShardBlockSize.to_string();
+    let v = ::get_xattr(&path, &key)?;
+    ::check_len(&key, &v, 8)?;
+    Ok(BigEndian::read_u64(&v))
+}
+
+/// Set shard block size(`trusted.glusterfs.shard.block-size`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::set_shard_block_size;
+///
+/// fn main() {
+///     let res = set_shard_block_size("/bricks/b1/vm1.img", 64 * 1024 * 1024);
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set shard block size: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_shard_block_size<P: XattrTarget>(path: P, block_size: u64) -> Result<()> {
+    let mut buf = [0; 8];
+    BigEndian::write_u64(&mut buf, block_size);
+    ::set_xattr(&path, &XattrKey::ShardBlockSize.to_string(), &buf)
+}
+
+/// Get aggregated file size(`trusted.glusterfs.shard.file-size`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::get_shard_file_size;
+///
+/// fn main() {
+///     let res = get_shard_file_size("/bricks/b1/vm1.img");
+///     match res {
+///         Ok(v) => println!("Size: {}, blocks: {}", v.size, v.block_count),
+///         Err(e) => println!("Failed to get shard file size: {}", e)
+///     }
+/// }
+/// ```
+pub fn get_shard_file_size<P: XattrTarget>(path: P) -> Result<ShardFileSize> {
+    let key = XattrKey::ShardFileSize.to_string();
+    ShardFileSize::decode(&key, &::get_xattr(&path, &key)?)
+}
+
+/// Set aggregated file size(`trusted.glusterfs.shard.file-size`)
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{set_shard_file_size, ShardFileSize};
+///
+/// fn main() {
+///     let res = set_shard_file_size("/bricks/b1/vm1.img", &ShardFileSize::new(1 << 30, 1 << 21));
+///     match res {
+///         Ok(_) => println!("OK"),
+///         Err(e) => println!("Failed to set shard file size: {}", e)
+///     }
+/// }
+/// ```
+pub fn set_shard_file_size<P: XattrTarget>(path: P, size: &ShardFileSize) -> Result<()> {
+    ::set_xattr(&path, &XattrKey::ShardFileSize.to_string(), &size.encode())
+}
+
+/// Shard indexes of the file `gfid` present in `.shard` of the brick
+fn list_shards(brick_root: &Path, gfid: &Gfid) -> Result<Vec<u64>> {
+    let entries = match fs::read_dir(brick_root.join(SHARD_DIR)) {
+        Ok(e) => e,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+
+    let prefix = format!("{}.", gfid);
+    let mut indexes = vec![];
+    for entry in entries {
+        let name = entry?.file_name();
+        let index = name
+            .to_str()
+            .and_then(|n| n.strip_prefix(prefix.as_str()))
+            .and_then(|n| n.parse().ok());
+        if let Some(index) = index {
+            indexes.push(index);
+        }
+    }
+    indexes.sort();
+    Ok(indexes)
+}
+
+/// Compare the shards of the file `gfid` on the brick with the shards
+/// expected for `file_size`(from `get_shard_file_size` of the base file)
+///
+/// On distributed volumes the shards are spread across the bricks, the
+/// missing shards may be on the other bricks.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{check_shards, get_gfid_typed, get_shard_block_size, get_shard_file_size};
+///
+/// fn main() {
+///     let base = "/bricks/b1/vm1.img";
+///     let res = get_gfid_typed(base).and_then(|gfid| {
+///         let block_size = get_shard_block_size(base)?;
+///         let file_size = get_shard_file_size(base)?;
+///         check_shards("/bricks/b1", &gfid, block_size, file_size.size)
+///     });
+///     match res {
+///         Ok(r) => println!("Missing shards: {:?}", r.missing),
+///         Err(e) => println!("Failed to check shards: {}", e)
+///     }
+/// }
+/// ```
+pub fn check_shards<B: AsRef<Path>>(brick_root: B, gfid: &Gfid, block_size: u64, file_size: u64) -> Result<ShardReport> {
+    let present = list_shards(brick_root.as_ref(), gfid)?;
+    let count = shard_count(file_size, block_size);
+    let missing = (1..count).filter(|i| present.binary_search(i).is_err()).collect();
+    let extra = present.iter().cloned().filter(|&i| i >= count).collect();
+    Ok(ShardReport { present, missing, extra })
+}
+
+#[test]
+fn test_shard_file_size_encode_decode() {
+    let mut v = [0; 32];
+    v[7] = 0x10;
+    v[23] = 0x08;
+    let size = ShardFileSize::decode("x", &v).unwrap();
+    assert_eq!(ShardFileSize::new(16, 8), size);
+    assert_eq!(v, size.encode());
+    assert!(ShardFileSize::decode("x", &v[..24]).is_err());
+
+    assert_eq!(1, shard_count(0, 4));
+    assert_eq!(1, shard_count(4, 4));
+    assert_eq!(2, shard_count(5, 4));
+}
+
+#[test]
+fn test_check_shards() {
+    let brick = ::testutil::TempDir::new("shard");
+    let gfid: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+    let other: Gfid = "af95963b-bbe6-49cb-bf6d-db7260ea6f72".parse().unwrap();
+    fs::create_dir_all(brick.join(SHARD_DIR)).unwrap();
+    for i in &[1, 3, 5] {
+        fs::File::create(shard_path(&brick, &gfid, *i)).unwrap();
+    }
+    fs::File::create(shard_path(&brick, &other, 2)).unwrap();
+
+    let report = check_shards(&brick, &gfid, 4, 17).unwrap();
+    assert_eq!(vec![1, 3, 5], report.present);
+    assert_eq!(vec![2, 4], report.missing);
+    assert_eq!(vec![5], report.extra);
+}
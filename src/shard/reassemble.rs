@@ -0,0 +1,224 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::{get_shard_block_size, get_shard_file_size, shard_count, shard_path};
+use dht::is_linkto_file;
+use error::Result;
+use gfid::Gfid;
+
+/// Reason a copy of a shard is not used
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShardProblem {
+    /// GFID(`trusted.gfid`) is not set
+    MissingGfid,
+    /// GFID differs from the other copies of the shard
+    GfidMismatch { expected: Gfid, actual: Gfid },
+    /// Bigger than the shard block size
+    Oversized(u64),
+}
+
+/// Copy of a shard skipped during the reassembly
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidShard {
+    pub index: u64,
+    pub path: PathBuf,
+    pub problem: ShardProblem,
+}
+
+/// Result of `reassemble_shards`, indexes are sorted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReassembleReport {
+    /// GFID of the base file
+    pub gfid: Gfid,
+    pub block_size: u64,
+    pub file_size: u64,
+    /// Pieces written to the output, the base file is the index 0
+    pub written: Vec<u64>,
+    /// Shards not found on any brick, left as holes
+    pub missing: Vec<u64>,
+    /// Copies skipped, shards without any usable copy are left as holes
+    pub invalid: Vec<InvalidShard>,
+}
+
+struct ShardCopy {
+    path: PathBuf,
+    gfid: Option<Gfid>,
+    size: u64,
+}
+
+/// Copies of the shard `index` on the bricks, DHT linkto files are not
+/// copies
+fn find_copies<B: AsRef<Path>>(bricks: &[B], gfid: &Gfid, index: u64) -> Result<Vec<ShardCopy>> {
+    let mut copies = vec![];
+    for brick in bricks {
+        let path = shard_path(brick, gfid, index);
+        let meta = match fs::symlink_metadata(&path) {
+            Ok(m) => m,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if !meta.is_file() || is_linkto_file(&path)? {
+            continue;
+        }
+        let gfid = ::ignore_missing(::get_gfid_typed(&path))?;
+        copies.push(ShardCopy { path, gfid, size: meta.len() });
+    }
+    Ok(copies)
+}
+
+/// Copy of the shard to use. Copies are expected to have the GFID most
+/// of the copies agree on, the biggest of them is picked since a replica
+/// lagging behind has a shorter shard.
+fn pick_copy(index: u64, copies: Vec<ShardCopy>, block_size: u64, invalid: &mut Vec<InvalidShard>) -> Option<PathBuf> {
+    let count = |g: Gfid| copies.iter().filter(|c| c.gfid == Some(g)).count();
+    let mut expected: Option<Gfid> = None;
+    for g in copies.iter().filter_map(|c| c.gfid) {
+        if expected.is_none_or(|e| count(g) > count(e)) {
+            expected = Some(g);
+        }
+    }
+
+    let mut best: Option<ShardCopy> = None;
+    for c in copies {
+        let problem = match (c.gfid, expected) {
+            (None, _) => Some(ShardProblem::MissingGfid),
+            (Some(actual), Some(expected)) if actual != expected => {
+                Some(ShardProblem::GfidMismatch { expected, actual })
+            }
+            _ if c.size > block_size => Some(ShardProblem::Oversized(c.size)),
+            _ => None,
+        };
+        match problem {
+            Some(problem) => invalid.push(InvalidShard { index, path: c.path, problem }),
+            None => {
+                if best.as_ref().is_none_or(|b| c.size > b.size) {
+                    best = Some(c);
+                }
+            }
+        }
+    }
+    best.map(|c| c.path)
+}
+
+/// Write the pieces at their offsets to a new sparse file of `file_size`
+/// bytes, the pieces not listed are holes
+fn write_image(output: &Path, block_size: u64, file_size: u64, pieces: &[(u64, PathBuf)]) -> Result<()> {
+    let mut out = OpenOptions::new().write(true).create_new(true).open(output)?;
+    out.set_len(file_size)?;
+    for &(index, ref path) in pieces {
+        let offset = index * block_size;
+        if offset >= file_size {
+            continue;
+        }
+        let limit = block_size.min(file_size - offset);
+        out.seek(SeekFrom::Start(offset))?;
+        io::copy(&mut File::open(path)?.take(limit), &mut out)?;
+    }
+    out.sync_all()?;
+    Ok(())
+}
+
+/// Rebuild a sharded file from the bricks into a new file `output`
+///
+/// `base` is the base file on any of the bricks, its GFID, block size
+/// and file size(`trusted.glusterfs.shard.*`) are used to find the shards
+/// in `.shard` of each brick in `bricks`. Shards with no usable copy are
+/// left as holes in the output. `output` must not exist.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::reassemble_shards;
+///
+/// fn main() {
+///     let bricks = ["/bricks/b1", "/bricks/b2", "/bricks/b3"];
+///     match reassemble_shards("/bricks/b1/vm1.img", &bricks, "/recovery/vm1.img") {
+///         Ok(r) => println!("Written: {}, missing: {:?}", r.written.len(), r.missing),
+///         Err(e) => println!("Failed to reassemble: {}", e)
+///     }
+/// }
+/// ```
+pub fn reassemble_shards<P, B, O>(base: P, bricks: &[B], output: O) -> Result<ReassembleReport>
+where
+    P: AsRef<Path>,
+    B: AsRef<Path>,
+    O: AsRef<Path>,
+{
+    let base = base.as_ref();
+    let gfid = ::get_gfid_typed(base)?;
+    let block_size = get_shard_block_size(base)?;
+    let file_size = get_shard_file_size(base)?.size;
+
+    let mut pieces = vec![(0, base.to_path_buf())];
+    let mut missing = vec![];
+    let mut invalid = vec![];
+    for index in 1..shard_count(file_size, block_size) {
+        let copies = find_copies(bricks, &gfid, index)?;
+        if copies.is_empty() {
+            missing.push(index);
+        } else if let Some(path) = pick_copy(index, copies, block_size, &mut invalid) {
+            pieces.push((index, path));
+        }
+    }
+
+    write_image(output.as_ref(), block_size, file_size, &pieces)?;
+    Ok(ReassembleReport {
+        gfid,
+        block_size,
+        file_size,
+        written: pieces.iter().map(|p| p.0).collect(),
+        missing,
+        invalid,
+    })
+}
+
+#[test]
+fn test_pick_shard_copy() {
+    let g1: Gfid = "0a118af0-3c20-4bdd-aded-694a17af6b5a".parse().unwrap();
+    let g2: Gfid = "af95963b-bbe6-49cb-bf6d-db7260ea6f72".parse().unwrap();
+    let copy = |p: &str, gfid, size| ShardCopy { path: PathBuf::from(p), gfid, size };
+
+    let mut invalid = vec![];
+    let copies = vec![
+        copy("b1", Some(g2), 4),
+        copy("b2", Some(g1), 2),
+        copy("b3", Some(g1), 3),
+        copy("b4", None, 4),
+        copy("b5", Some(g1), 5),
+    ];
+    assert_eq!(Some(PathBuf::from("b3")), pick_copy(1, copies, 4, &mut invalid));
+    let problems: Vec<(&str, ShardProblem)> =
+        invalid.iter().map(|i| (i.path.to_str().unwrap(), i.problem)).collect();
+    assert_eq!(
+        vec![
+            ("b1", ShardProblem::GfidMismatch { expected: g1, actual: g2 }),
+            ("b4", ShardProblem::MissingGfid),
+            ("b5", ShardProblem::Oversized(5)),
+        ],
+        problems
+    );
+
+    let mut invalid = vec![];
+    assert_eq!(None, pick_copy(1, vec![copy("b1", None, 1)], 4, &mut invalid));
+    assert_eq!(1, invalid.len());
+}
+
+#[test]
+fn test_write_sparse_image() {
+    use std::io::Write;
+
+    let dir = ::testutil::TempDir::new("reassemble");
+    File::create(dir.join("0")).unwrap().write_all(b"aaaa").unwrap();
+    File::create(dir.join("2")).unwrap().write_all(b"cccc").unwrap();
+    File::create(dir.join("3")).unwrap().write_all(b"dddd").unwrap();
+
+    let pieces = vec![(0, dir.join("0")), (2, dir.join("2")), (3, dir.join("3"))];
+    write_image(&dir.join("out"), 4, 14, &pieces).unwrap();
+    let mut data = vec![];
+    File::open(dir.join("out")).unwrap().read_to_end(&mut data).unwrap();
+    assert_eq!(b"aaaa\0\0\0\0ccccdd".to_vec(), data);
+    assert!(write_image(&dir.join("out"), 4, 14, &pieces).is_err());
+}
@@ -0,0 +1,500 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use std::os::unix::fs::MetadataExt;
+
+use afr::{get_afr_dirty, get_afr_pending, AfrChangelog};
+use error::{GlusterXattrError, Result};
+use gfid::Gfid;
+use key::XattrKey;
+
+/// Bricks of one replica set, in the order of the volume info
+///
+/// Brick `i` of the set is `<volume>-client-<first_client + i>` in the
+/// AFR xattr names.
+#[derive(Clone, Debug)]
+pub struct ReplicaSet {
+    pub volume: String,
+    pub first_client: u32,
+    pub bricks: Vec<PathBuf>,
+}
+
+impl ReplicaSet {
+    pub fn new<P: AsRef<Path>>(volume: &str, first_client: u32, bricks: &[P]) -> ReplicaSet {
+        ReplicaSet {
+            volume: volume.to_string(),
+            first_client,
+            bricks: bricks.iter().map(|b| b.as_ref().to_path_buf()).collect(),
+        }
+    }
+
+    /// Path of the file on the given brick, `file` is relative to the
+    /// brick root
+    pub fn brick_path<P: AsRef<Path>>(&self, brick: usize, file: P) -> PathBuf {
+        ::brick_join(&self.bricks[brick], file)
+    }
+}
+
+/// GFID of the file on one brick of the replica set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrickGfid {
+    /// File is missing on the brick
+    Missing,
+    /// File exists without GFID(`trusted.gfid`), for example after an
+    /// interrupted create
+    NotSet,
+    Set(Gfid),
+}
+
+impl BrickGfid {
+    /// GFID if the file exists on the brick with a GFID
+    pub fn gfid(&self) -> Option<Gfid> {
+        match *self {
+            BrickGfid::Set(g) => Some(g),
+            _ => None,
+        }
+    }
+}
+
+/// Heal state of one type of operation(data, metadata or entry),
+/// sources and sinks are brick indexes in the replica set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HealState {
+    Healthy,
+    /// `sinks` is empty if only the dirty counters are set, self-heal
+    /// picks the direction in that case
+    NeedsHeal { sources: Vec<usize>, sinks: Vec<usize> },
+    SplitBrain,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SplitBrainType {
+    Data,
+    Metadata,
+    Entry,
+    Gfid,
+}
+
+/// Overall state of the file across the replica set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplicaStatus {
+    Healthy,
+    NeedsHeal { sources: Vec<usize>, sinks: Vec<usize> },
+    SplitBrain(Vec<SplitBrainType>),
+}
+
+/// Xattrs collected from all the bricks of the replica set and the
+/// analysis based on them
+#[derive(Clone, Debug)]
+pub struct ReplicaReport {
+    /// GFID on each brick
+    pub gfids: Vec<BrickGfid>,
+    /// Accusation matrix, `matrix[i][j]` is the pending counters
+    /// brick `i` holds against brick `j`
+    pub matrix: Vec<Vec<AfrChangelog>>,
+    pub dirty: Vec<AfrChangelog>,
+    pub data: HealState,
+    pub metadata: HealState,
+    pub entry: HealState,
+    pub status: ReplicaStatus,
+}
+
+impl ReplicaReport {
+    fn new(gfids: Vec<BrickGfid>, matrix: Vec<Vec<AfrChangelog>>, dirty: Vec<AfrChangelog>) -> ReplicaReport {
+        // Bricks without the GFID can only be sinks
+        let present: Vec<bool> = gfids.iter().map(|g| g.gfid().is_some()).collect();
+        let data = heal_state(&matrix, &dirty, &present, |c| c.data);
+        let metadata = heal_state(&matrix, &dirty, &present, |c| c.metadata);
+        let entry = heal_state(&matrix, &dirty, &present, |c| c.entry);
+
+        let mut split_brains = vec![];
+        for &(state, typ) in &[
+            (&data, SplitBrainType::Data),
+            (&metadata, SplitBrainType::Metadata),
+            (&entry, SplitBrainType::Entry),
+        ] {
+            if *state == HealState::SplitBrain {
+                split_brains.push(typ);
+            }
+        }
+        let mut unique_gfids: Vec<Gfid> = gfids.iter().filter_map(|g| g.gfid()).collect();
+        unique_gfids.sort();
+        unique_gfids.dedup();
+        if unique_gfids.len() > 1 {
+            split_brains.push(SplitBrainType::Gfid);
+        }
+
+        let status = if !split_brains.is_empty() {
+            ReplicaStatus::SplitBrain(split_brains)
+        } else {
+            overall_heal(&[&data, &metadata, &entry], &present)
+        };
+
+        ReplicaReport { gfids, matrix, dirty, data, metadata, entry, status }
+    }
+}
+
+fn heal_state<F>(matrix: &[Vec<AfrChangelog>], dirty: &[AfrChangelog], present: &[bool], counter: F) -> HealState
+where
+    F: Fn(&AfrChangelog) -> u32,
+{
+    let n = present.len();
+    // No copy with a GFID to compare, all the bricks are sinks of the
+    // overall status
+    if !present.contains(&true) {
+        return HealState::Healthy;
+    }
+    let mut accused = vec![false; n];
+    for i in (0..n).filter(|&i| present[i]) {
+        for j in (0..n).filter(|&j| j != i && present[j]) {
+            if counter(&matrix[i][j]) > 0 {
+                accused[j] = true;
+            }
+        }
+    }
+
+    let sources: Vec<usize> = (0..n).filter(|&i| present[i] && !accused[i]).collect();
+    let sinks: Vec<usize> = (0..n).filter(|&i| present[i] && accused[i]).collect();
+    let is_dirty = (0..n).any(|i| present[i] && counter(&dirty[i]) > 0);
+
+    if sources.is_empty() {
+        HealState::SplitBrain
+    } else if sinks.is_empty() && !is_dirty {
+        HealState::Healthy
+    } else {
+        HealState::NeedsHeal { sources, sinks }
+    }
+}
+
+fn overall_heal(states: &[&HealState], present: &[bool]) -> ReplicaStatus {
+    let mut sources: Vec<usize> = (0..present.len()).filter(|&i| present[i]).collect();
+    let mut sinks: Vec<usize> = (0..present.len()).filter(|&i| !present[i]).collect();
+    let mut needs_heal = !sinks.is_empty();
+
+    for state in states {
+        if let HealState::NeedsHeal { sources: ref src, sinks: ref snk } = **state {
+            needs_heal = true;
+            sources.retain(|i| src.contains(i));
+            sinks.extend(snk);
+        }
+    }
+
+    if !needs_heal {
+        return ReplicaStatus::Healthy;
+    }
+    sinks.sort();
+    sinks.dedup();
+    ReplicaStatus::NeedsHeal { sources, sinks }
+}
+
+/// Read the AFR changelog and GFID of the file from all the bricks of
+/// the replica set and find whether it needs heal or is in split-brain
+///
+/// `file` is the path relative to the brick root. Bricks not having the
+/// file, or having it without GFID, are reported as sinks.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{analyze_replica, ReplicaSet};
+///
+/// fn main() {
+///     let replica = ReplicaSet::new("gv0", 0, &["/bricks/b1", "/bricks/b2", "/bricks/b3"]);
+///     match analyze_replica(&replica, "dir1/f1") {
+///         Ok(report) => println!("Status: {:?}", report.status),
+///         Err(e) => println!("Failed to analyze: {}", e)
+///     }
+/// }
+/// ```
+pub fn analyze_replica<P: AsRef<Path>>(replica: &ReplicaSet, file: P) -> Result<ReplicaReport> {
+    let n = replica.bricks.len();
+    let mut gfids = Vec::with_capacity(n);
+    let mut matrix = Vec::with_capacity(n);
+    let mut dirty = Vec::with_capacity(n);
+
+    for i in 0..n {
+        let path = replica.brick_path(i, &file);
+        match fs::symlink_metadata(&path) {
+            Ok(_) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                gfids.push(BrickGfid::Missing);
+                matrix.push(vec![AfrChangelog::default(); n]);
+                dirty.push(AfrChangelog::default());
+                continue;
+            }
+            Err(e) => return Err(GlusterXattrError::Io(e)),
+        }
+
+        gfids.push(match ::ignore_missing(::get_gfid_typed(&path))? {
+            Some(g) => BrickGfid::Set(g),
+            None => BrickGfid::NotSet,
+        });
+        let mut row = Vec::with_capacity(n);
+        for j in 0..n {
+            let client = replica.first_client + j as u32;
+            row.push(::ignore_missing(get_afr_pending(&path, &replica.volume, client))?.unwrap_or_default());
+        }
+        matrix.push(row);
+        dirty.push(::ignore_missing(get_afr_dirty(&path))?.unwrap_or_default());
+    }
+
+    if gfids.iter().all(|g| *g == BrickGfid::Missing) {
+        return Err(GlusterXattrError::Io(io::Error::from(io::ErrorKind::NotFound)));
+    }
+
+    Ok(ReplicaReport::new(gfids, matrix, dirty))
+}
+
+/// How to choose the source brick while resolving split-brain
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HealSource {
+    /// Brick index in the replica set
+    Brick(usize),
+    /// Brick with the biggest file
+    BiggerFile,
+    /// Brick with the latest modification time
+    LatestMtime,
+    /// Brick whose size and mtime matches more than half of the bricks
+    Majority,
+}
+
+/// AFR pending xattr to be written on a brick
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AfrWrite {
+    pub brick: usize,
+    pub path: PathBuf,
+    pub key: XattrKey,
+    pub value: AfrChangelog,
+}
+
+fn pick_unique<F>(stats: &[Option<(u64, i64)>], policy: &str, value: F) -> Result<usize>
+where
+    F: Fn(&(u64, i64)) -> i64,
+{
+    let best = stats.iter().filter_map(|s| s.as_ref().map(&value)).max();
+    let candidates: Vec<usize> = (0..stats.len())
+        .filter(|&i| stats[i].as_ref().map(&value) == best)
+        .collect();
+    match candidates.as_slice() {
+        [idx] => Ok(*idx),
+        [] => Err(GlusterXattrError::NoHealSource("file is missing on all bricks".to_string())),
+        _ => Err(GlusterXattrError::NoHealSource(format!("more than one brick has the {}", policy))),
+    }
+}
+
+/// Brick index chosen by the policy, `stats` is size and mtime on each
+/// brick, `None` if the file is missing
+fn choose_source(stats: &[Option<(u64, i64)>], source: HealSource) -> Result<usize> {
+    match source {
+        HealSource::Brick(idx) => match stats.get(idx) {
+            Some(&Some(_)) => Ok(idx),
+            _ => Err(GlusterXattrError::NoHealSource(format!("file is not present on brick {}", idx))),
+        },
+        HealSource::BiggerFile => pick_unique(stats, "biggest file", |s| s.0 as i64),
+        HealSource::LatestMtime => pick_unique(stats, "latest mtime", |s| s.1),
+        HealSource::Majority => (0..stats.len())
+            .find(|&i| {
+                stats[i].is_some() && stats.iter().filter(|s| **s == stats[i]).count() * 2 > stats.len()
+            })
+            .ok_or_else(|| GlusterXattrError::NoHealSource("no majority of bricks agree".to_string())),
+    }
+}
+
+/// AFR pending xattrs to write so that `src` becomes the only source for
+/// the given types: the source accuses every other brick and the other
+/// bricks stop accusing the source
+fn plan_writes(replica: &ReplicaSet, file: &Path, report: &ReplicaReport, src: usize, types: &[SplitBrainType]) -> Vec<AfrWrite> {
+    let set = |c: &mut AfrChangelog, typ: SplitBrainType, accuse: bool| {
+        let counter = match typ {
+            SplitBrainType::Data => &mut c.data,
+            SplitBrainType::Metadata => &mut c.metadata,
+            SplitBrainType::Entry => &mut c.entry,
+            SplitBrainType::Gfid => return,
+        };
+        if !accuse {
+            *counter = 0;
+        } else if *counter == 0 {
+            *counter = 1;
+        }
+    };
+
+    let mut writes = vec![];
+    for i in (0..replica.bricks.len()).filter(|&i| report.gfids[i].gfid().is_some()) {
+        let targets: Vec<usize> = if i == src {
+            (0..replica.bricks.len()).filter(|&j| j != src && report.gfids[j].gfid().is_some()).collect()
+        } else {
+            vec![src]
+        };
+
+        for j in targets {
+            let mut value = report.matrix[i][j];
+            for typ in types {
+                set(&mut value, *typ, i == src);
+            }
+            if value != report.matrix[i][j] {
+                writes.push(AfrWrite {
+                    brick: i,
+                    path: replica.brick_path(i, file),
+                    key: XattrKey::AfrPending(replica.volume.clone(), replica.first_client + j as u32),
+                    value,
+                });
+            }
+        }
+    }
+    writes
+}
+
+/// Resolve data, metadata and entry split-brain of the file by marking
+/// the chosen brick as the heal source, self-heal daemon then heals the
+/// other bricks from it
+///
+/// Returns the AFR xattrs written, with `dry_run` the xattrs are only
+/// returned and not written. Returns empty list if the file is not in
+/// split-brain. GFID split-brain can not be resolved using AFR xattrs
+/// and is ignored.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{resolve_split_brain, HealSource, ReplicaSet};
+///
+/// fn main() {
+///     let replica = ReplicaSet::new("gv0", 0, &["/bricks/b1", "/bricks/b2"]);
+///     match resolve_split_brain(&replica, "dir1/f1", HealSource::LatestMtime, true) {
+///         Ok(writes) => for w in writes {
+///             println!("setfattr -n {} on {}: {:?}", w.key, w.path.display(), w.value)
+///         },
+///         Err(e) => println!("Failed to resolve split-brain: {}", e)
+///     }
+/// }
+/// ```
+pub fn resolve_split_brain<P: AsRef<Path>>(replica: &ReplicaSet, file: P, source: HealSource, dry_run: bool) -> Result<Vec<AfrWrite>> {
+    let file = file.as_ref();
+    let report = analyze_replica(replica, file)?;
+    let types: Vec<SplitBrainType> = match report.status {
+        ReplicaStatus::SplitBrain(ref types) => types.iter().cloned().filter(|t| *t != SplitBrainType::Gfid).collect(),
+        _ => vec![],
+    };
+    if types.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut stats = Vec::with_capacity(replica.bricks.len());
+    for i in 0..replica.bricks.len() {
+        stats.push(match report.gfids[i] {
+            BrickGfid::Set(_) => {
+                let meta = fs::symlink_metadata(replica.brick_path(i, file))?;
+                Some((meta.size(), meta.mtime()))
+            }
+            _ => None,
+        });
+    }
+
+    let src = choose_source(&stats, source)?;
+    let writes = plan_writes(replica, file, &report, src, &types);
+    if !dry_run {
+        for w in &writes {
+            ::set_xattr(&w.path, &w.key.to_string(), &w.value.encode())?;
+        }
+    }
+    Ok(writes)
+}
+
+#[test]
+fn test_replica_report() {
+    let gfid: Gfid = "bb74c663-2552-41aa-a0ae-d4d94d9dd187".parse().unwrap();
+    let clean = AfrChangelog::default();
+    let data = AfrChangelog::new(2, 0, 0);
+
+    let report = ReplicaReport::new(vec![BrickGfid::Set(gfid); 3], vec![vec![clean; 3]; 3], vec![clean; 3]);
+    assert_eq!(ReplicaStatus::Healthy, report.status);
+
+    // Bricks 0 and 1 accuse brick 2
+    let matrix = vec![vec![clean, clean, data], vec![clean, clean, data], vec![clean; 3]];
+    let report = ReplicaReport::new(vec![BrickGfid::Set(gfid); 3], matrix, vec![clean; 3]);
+    assert_eq!(HealState::NeedsHeal { sources: vec![0, 1], sinks: vec![2] }, report.data);
+    assert_eq!(HealState::Healthy, report.metadata);
+    assert_eq!(ReplicaStatus::NeedsHeal { sources: vec![0, 1], sinks: vec![2] }, report.status);
+
+    // Bricks 0 and 1 accuse each other
+    let matrix = vec![vec![clean, data], vec![data, clean]];
+    let report = ReplicaReport::new(vec![BrickGfid::Set(gfid); 2], matrix, vec![clean; 2]);
+    assert_eq!(ReplicaStatus::SplitBrain(vec![SplitBrainType::Data]), report.status);
+
+    // Different GFIDs and file missing on one brick
+    let other: Gfid = "5e3ac1f8-6f1e-4c06-8a9d-0e3a8d9f61a2".parse().unwrap();
+    let gfids = vec![BrickGfid::Set(gfid), BrickGfid::Set(other), BrickGfid::Missing];
+    let report = ReplicaReport::new(gfids, vec![vec![clean; 3]; 3], vec![clean; 3]);
+    assert_eq!(ReplicaStatus::SplitBrain(vec![SplitBrainType::Gfid]), report.status);
+    let gfids = vec![BrickGfid::Set(gfid), BrickGfid::Set(gfid), BrickGfid::Missing];
+    let report = ReplicaReport::new(gfids, vec![vec![clean; 3]; 3], vec![clean; 3]);
+    assert_eq!(ReplicaStatus::NeedsHeal { sources: vec![0, 1], sinks: vec![2] }, report.status);
+}
+
+#[test]
+fn test_replica_report_without_gfid() {
+    let gfid: Gfid = "bb74c663-2552-41aa-a0ae-d4d94d9dd187".parse().unwrap();
+    let clean = AfrChangelog::default();
+
+    // Interrupted create, brick 1 has the file without GFID
+    let gfids = vec![BrickGfid::Set(gfid), BrickGfid::NotSet, BrickGfid::Set(gfid)];
+    let report = ReplicaReport::new(gfids, vec![vec![clean; 3]; 3], vec![clean; 3]);
+    assert_eq!(HealState::Healthy, report.data);
+    assert_eq!(ReplicaStatus::NeedsHeal { sources: vec![0, 2], sinks: vec![1] }, report.status);
+
+    // Accusations from the brick without GFID are ignored
+    let matrix = vec![vec![clean; 3], vec![AfrChangelog::new(1, 0, 0), clean, clean], vec![clean; 3]];
+    let gfids = vec![BrickGfid::Set(gfid), BrickGfid::NotSet, BrickGfid::Missing];
+    let report = ReplicaReport::new(gfids, matrix, vec![clean; 3]);
+    assert_eq!(ReplicaStatus::NeedsHeal { sources: vec![0], sinks: vec![1, 2] }, report.status);
+}
+
+#[test]
+fn test_split_brain_resolution_plan() {
+    let gfid: Gfid = "bb74c663-2552-41aa-a0ae-d4d94d9dd187".parse().unwrap();
+    let clean = AfrChangelog::default();
+    let replica = ReplicaSet::new("gv0", 3, &["/b1", "/b2", "/b3"]);
+
+    // Brick 0 and 1 accuse each other, brick 2 has no opinion
+    let matrix = vec![
+        vec![clean, AfrChangelog::new(4, 0, 0), clean],
+        vec![AfrChangelog::new(2, 0, 0), clean, clean],
+        vec![clean; 3],
+    ];
+    let report = ReplicaReport::new(vec![BrickGfid::Set(gfid); 3], matrix, vec![clean; 3]);
+    let writes = plan_writes(&replica, Path::new("f1"), &report, 1, &[SplitBrainType::Data]);
+    let summary: Vec<(usize, String, AfrChangelog)> = writes.into_iter().map(|w| (w.brick, w.key.to_string(), w.value)).collect();
+    assert_eq!(
+        vec![
+            (0, "trusted.afr.gv0-client-4".to_string(), clean),
+            (1, "trusted.afr.gv0-client-5".to_string(), AfrChangelog::new(1, 0, 0)),
+        ],
+        summary
+    );
+
+    let stats = vec![Some((10, 100)), Some((20, 50)), Some((10, 100))];
+    assert_eq!(1, choose_source(&stats, HealSource::BiggerFile).unwrap());
+    assert!(choose_source(&stats, HealSource::LatestMtime).is_err());
+    assert_eq!(0, choose_source(&stats, HealSource::Majority).unwrap());
+    assert!(choose_source(&[Some((1, 1)), None], HealSource::Brick(1)).is_err());
+}
+
+#[test]
+fn test_analyze_replica_without_gfid() {
+    let dir = ::testutil::TempDir::new("splitbrain");
+    let replica = ReplicaSet::new("gv0", 0, &[dir.join("b1"), dir.join("b2")]);
+    fs::create_dir_all(&replica.bricks[0]).unwrap();
+    fs::create_dir_all(&replica.bricks[1]).unwrap();
+    fs::File::create(replica.brick_path(0, "f1")).unwrap();
+
+    // Files in the temporary directory have no GFID
+    let report = analyze_replica(&replica, "f1").unwrap();
+    assert_eq!(vec![BrickGfid::NotSet, BrickGfid::Missing], report.gfids);
+    assert_eq!(ReplicaStatus::NeedsHeal { sources: vec![], sinks: vec![0, 1] }, report.status);
+    assert!(analyze_replica(&replica, "f2").is_err());
+}
@@ -0,0 +1,82 @@
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+
+use xattr;
+
+/// File whose xattrs are accessed by the functions of this crate
+///
+/// Implemented for everything that implements `AsRef<Path>`, symlinks
+/// are not followed so the xattrs of the symlink itself are accessed
+/// like Gluster's posix translator does. Wrap the path in
+/// `FollowSymlinks` to access the xattrs of the symlink target.
+pub trait XattrTarget {
+    /// Get the raw value, `None` if the xattr is not set
+    fn get_raw(&self, name: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Set the raw value
+    fn set_raw(&self, name: &str, value: &[u8]) -> io::Result<()>;
+
+    /// Remove the xattr
+    fn remove_raw(&self, name: &str) -> io::Result<()>;
+
+    /// Names of all the xattrs set
+    fn list_raw(&self) -> io::Result<Vec<OsString>>;
+}
+
+impl<P: AsRef<Path> + ?Sized> XattrTarget for P {
+    fn get_raw(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        xattr::get(self, name)
+    }
+
+    fn set_raw(&self, name: &str, value: &[u8]) -> io::Result<()> {
+        xattr::set(self, name, value)
+    }
+
+    fn remove_raw(&self, name: &str) -> io::Result<()> {
+        xattr::remove(self, name)
+    }
+
+    fn list_raw(&self) -> io::Result<Vec<OsString>> {
+        xattr::list(self).map(|names| names.collect())
+    }
+}
+
+/// Path wrapper to access the xattrs of the symlink target instead
+/// of the symlink itself
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::{get_gfid, FollowSymlinks};
+///
+/// fn main() {
+///     let res = get_gfid(FollowSymlinks("/bricks/b1/symlink1"));
+///     match res {
+///         Ok(v) => println!("GFID of the target: {}", v),
+///         Err(e) => println!("Failed to get GFID: {}", e)
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct FollowSymlinks<P>(pub P);
+
+impl<P: AsRef<Path>> XattrTarget for FollowSymlinks<P> {
+    fn get_raw(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        xattr::get_deref(&self.0, name)
+    }
+
+    fn set_raw(&self, name: &str, value: &[u8]) -> io::Result<()> {
+        xattr::set_deref(&self.0, name, value)
+    }
+
+    fn remove_raw(&self, name: &str) -> io::Result<()> {
+        xattr::remove_deref(&self.0, name)
+    }
+
+    fn list_raw(&self) -> io::Result<Vec<OsString>> {
+        xattr::list_deref(&self.0).map(|names| names.collect())
+    }
+}
@@ -0,0 +1,27 @@
+use std::fs;
+use std::path::Path;
+
+use error::Result;
+
+/// Walk the directory tree of a brick depth first, `f` is called with
+/// the path and metadata(symlinks not followed) of every entry below
+/// `dir`. Gluster's `.glusterfs` directory at the brick root is skipped.
+pub(crate) fn walk<F>(brick_root: &Path, dir: &Path, f: &mut F) -> Result<()>
+where
+    F: FnMut(&Path, &fs::Metadata) -> Result<()>,
+{
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if dir == brick_root && entry.file_name() == ".glusterfs" {
+            continue;
+        }
+
+        let meta = fs::symlink_metadata(&path)?;
+        f(&path, &meta)?;
+        if meta.is_dir() {
+            walk(brick_root, &path, f)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use std::cmp::Ordering;
+use std::ops::{Add, Sub};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use error::{GlusterXattrError, Result};
+
+/// Xtime/Stime value, seconds and microseconds since the Unix epoch
+///
+/// Unset stime is represented by `Xtime::URXTIME` which compares less
+/// than every other value, so `xtime > stime` holds for unsynced files.
+///
+/// Examples:
+///
+/// ```
+/// extern crate glusterxattr;
+///
+/// use glusterxattr::Xtime;
+///
+/// fn main() {
+///     let stime = Xtime::URXTIME;
+///     let xtime = Xtime::now();
+///     assert!(xtime > stime);
+///     assert_eq!(Some(stime), Xtime::min_of(vec![xtime, stime]));
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Xtime(pub u32, pub u32);
+
+impl Xtime {
+    /// Sentinel Gluster uses for unset stime(`-1, 0`)
+    pub const URXTIME: Xtime = Xtime(u32::MAX, 0);
+
+    pub fn new(sec: u32, usec: u32) -> Xtime {
+        Xtime(sec, usec)
+    }
+
+    /// Current wall-clock time
+    pub fn now() -> Xtime {
+        Xtime::from_system_time(SystemTime::now()).unwrap_or(Xtime(0, 0))
+    }
+
+    pub fn sec(&self) -> u32 {
+        self.0
+    }
+
+    pub fn usec(&self) -> u32 {
+        self.1
+    }
+
+    /// `true` if this is the unset stime sentinel
+    pub fn is_urxtime(&self) -> bool {
+        *self == Xtime::URXTIME
+    }
+
+    /// Convert from a duration since the Unix epoch, seconds beyond
+    /// `u32` range are truncated
+    pub fn from_duration(d: Duration) -> Xtime {
+        Xtime(d.as_secs() as u32, d.subsec_micros())
+    }
+
+    /// Duration since the Unix epoch, `None` for `URXTIME`
+    pub fn to_duration(&self) -> Option<Duration> {
+        if self.is_urxtime() {
+            return None;
+        }
+        Some(Duration::new(u64::from(self.0), 0) + Duration::from_micros(u64::from(self.1)))
+    }
+
+    /// Convert from wall-clock time, `None` if the time is before
+    /// the Unix epoch
+    pub fn from_system_time(t: SystemTime) -> Option<Xtime> {
+        t.duration_since(UNIX_EPOCH).ok().map(Xtime::from_duration)
+    }
+
+    /// Wall-clock time, `None` for `URXTIME`
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        self.to_duration().map(|d| UNIX_EPOCH + d)
+    }
+
+    /// Time elapsed from `earlier` to `self`, `None` if `earlier` is
+    /// later or if either of them is `URXTIME`
+    pub fn duration_since(&self, earlier: Xtime) -> Option<Duration> {
+        match (self.to_duration(), earlier.to_duration()) {
+            (Some(a), Some(b)) if a >= b => Some(a - b),
+            _ => None,
+        }
+    }
+
+    /// Latest of the given values, for example xtime across bricks
+    pub fn max_of<I: IntoIterator<Item = Xtime>>(values: I) -> Option<Xtime> {
+        values.into_iter().max()
+    }
+
+    /// Earliest of the given values, for example stime across bricks
+    pub fn min_of<I: IntoIterator<Item = Xtime>>(values: I) -> Option<Xtime> {
+        values.into_iter().min()
+    }
+
+    pub(crate) fn decode(xattr_name: &str, value: &[u8]) -> Result<Xtime> {
+        if value.len() != 8 {
+            return Err(GlusterXattrError::Malformed {
+                name: xattr_name.to_string(),
+                expected: 8,
+                actual: value.len(),
+            });
+        }
+        Ok(Xtime(BigEndian::read_u32(&value[0..4]), BigEndian::read_u32(&value[4..8])))
+    }
+
+    pub(crate) fn encode(&self) -> [u8; 8] {
+        let mut buf = [0; 8];
+        BigEndian::write_u32(&mut buf[0..4], self.0);
+        BigEndian::write_u32(&mut buf[4..8], self.1);
+        buf
+    }
+}
+
+impl Ord for Xtime {
+    fn cmp(&self, other: &Xtime) -> Ordering {
+        match (self.is_urxtime(), other.is_urxtime()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => (self.0, self.1).cmp(&(other.0, other.1)),
+        }
+    }
+}
+
+impl PartialOrd for Xtime {
+    fn partial_cmp(&self, other: &Xtime) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<Duration> for Xtime {
+    fn from(d: Duration) -> Xtime {
+        Xtime::from_duration(d)
+    }
+}
+
+impl Add<Duration> for Xtime {
+    type Output = Xtime;
+
+    fn add(self, d: Duration) -> Xtime {
+        Xtime::from_duration(self.to_duration().unwrap_or_default() + d)
+    }
+}
+
+impl Sub<Duration> for Xtime {
+    type Output = Xtime;
+
+    fn sub(self, d: Duration) -> Xtime {
+        let current = self.to_duration().unwrap_or_default();
+        Xtime::from_duration(current.checked_sub(d).unwrap_or_default())
+    }
+}
+
+#[test]
+fn test_xtime_ordering() {
+    assert!(Xtime(100, 2) < Xtime(100, 3));
+    assert!(Xtime(99, 999999) < Xtime(100, 0));
+    assert!(Xtime::URXTIME < Xtime(0, 0));
+    assert_eq!(Some(Xtime(200, 1)), Xtime::max_of(vec![Xtime(100, 5), Xtime(200, 1), Xtime::URXTIME]));
+    assert_eq!(Some(Xtime::URXTIME), Xtime::min_of(vec![Xtime(100, 5), Xtime::URXTIME]));
+    assert_eq!(None, Xtime::max_of(vec![]));
+}
+
+#[test]
+fn test_xtime_conversions() {
+    let t = UNIX_EPOCH + Duration::new(1481540557, 16683000);
+    let x = Xtime::from_system_time(t).unwrap();
+    assert_eq!(Xtime(1481540557, 16683), x);
+    assert_eq!(Some(t), x.to_system_time());
+    assert_eq!(None, Xtime::URXTIME.to_system_time());
+
+    assert_eq!(Xtime(101, 500000), Xtime(100, 700000) + Duration::from_micros(800000));
+    assert_eq!(Xtime(99, 900000), Xtime(100, 0) - Duration::from_micros(100000));
+    assert_eq!(Some(Duration::from_micros(1500000)), Xtime(101, 500000).duration_since(Xtime(100, 0)));
+    assert_eq!(None, Xtime(100, 0).duration_since(Xtime(101, 0)));
+
+    assert_eq!(Xtime(5, 6), Xtime::decode("x", &Xtime(5, 6).encode()).unwrap());
+    assert!(Xtime::decode("x", &[0; 7]).is_err());
+}